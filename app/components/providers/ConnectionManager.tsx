@@ -2,6 +2,7 @@
 
 import { useAtom, useSetAtom } from "jotai";
 import { useEffect, useState, createContext, useContext } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { checkBackendHealth, listSessions } from "@/lib/api-client";
 import { TauriService } from "@/lib/tauri-service";
@@ -48,6 +49,13 @@ export function ConnectionManager({ children }: ConnectionManagerProps) {
     if (typeof globalThis !== 'undefined' && (globalThis as any).window === undefined) return;
     
     console.log("🚀 ConnectionManager: Initializing backend state");
+
+    TauriService.getInstance()
+      .installBackendAuth()
+      .catch((error) => console.warn("Failed to install backend auth token:", error));
+    TauriService.getInstance()
+      .syncBackendUrl()
+      .catch((error) => console.warn("Failed to get the backend URL:", error));
     
     // Only reset backend state if it's not already ready
     // This prevents unnecessary resets during locale changes
@@ -84,13 +92,23 @@ export function ConnectionManager({ children }: ConnectionManagerProps) {
           setSidecarErrors((prev) => [...prev, `Spawn Error: ${event.payload}`]);
         });
 
-        await listen("sidecar-stdout", (event) => {
-          console.log("📤 Sidecar Stdout:", event.payload);
+        await listen<string[]>("sidecar-stdout-batch", (event) => {
+          event.payload.forEach((line) => console.log("📤 Sidecar Stdout:", line));
         });
 
         await listen("sidecar-stderr", (event) => {
           console.error("📥 Sidecar Stderr:", event.payload);
         });
+
+        // Replay output emitted before these listeners were registered
+        const buffered = await invoke<{ stream: string; line: string }[]>("get_sidecar_log_buffer");
+        buffered.forEach(({ stream, line }) => {
+          if (stream === "stderr") {
+            console.error("📥 Sidecar Stderr:", line);
+          } else {
+            console.log("📤 Sidecar Stdout:", line);
+          }
+        });
       } catch (error) {
         console.warn("Failed to setup sidecar event listeners:", error);
       }
@@ -118,6 +136,16 @@ export function ConnectionManager({ children }: ConnectionManagerProps) {
       console.log(`🔍 Health check attempt ${attempts}`);
       
       try {
+        // In Tauri, wait for the app's backend-ready signal instead of polling
+        // a backend that is still starting; polling stays as the fallback
+        if (attempts === 1 && TauriService.getInstance().isTauriMode()) {
+          setConnectionStatus("Waiting for the backend to start...");
+          await TauriService.getInstance()
+            .syncBackendUrl()
+            .catch((error) => {
+              console.warn("wait_for_backend failed, polling instead:", error);
+            });
+        }
         const isHealthy = await checkBackendHealth();
         if (isHealthy) {
           // Ensure backend is fully ready with session check
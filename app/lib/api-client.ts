@@ -5,6 +5,8 @@
  * Separate from Next.js API routes which are in app/api/route.ts files
  */
 
+import { getBackendUrl } from "./backend-url";
+
 // Types for API requests and responses
 export interface ChatMessage {
   role: "user" | "assistant" | "system";
@@ -37,12 +39,10 @@ export interface SessionInfo {
   configuration: Record<string, any>;
 }
 
-const PYTHON_BACKEND_URL =
-  process.env.NEXT_PUBLIC_PYTHON_BACKEND_URL || "http://localhost:8009";
 
 // ===== Model Management =====
 export async function getOllamaModelList(baseUrl: string, signal?: AbortSignal): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/llm/models/ollama`;
+  const url = `${getBackendUrl()}/llm/models/ollama`;
   const response = await fetch(url, {
     method: "GET",
     headers: { "Content-Type": "application/json" },
@@ -56,7 +56,7 @@ export async function getOllamaModelList(baseUrl: string, signal?: AbortSignal):
 }
 
 export async function getModelSuggestions(provider: string, partialModel: string = "", baseUrl?: string): Promise<any> {
-  let url = `${PYTHON_BACKEND_URL}/llm/models/suggestions/${provider}?partial_model=${encodeURIComponent(partialModel)}`;
+  let url = `${getBackendUrl()}/llm/models/suggestions/${provider}?partial_model=${encodeURIComponent(partialModel)}`;
   if (baseUrl) {
     url += `&base_url=${encodeURIComponent(baseUrl)}`;
   }
@@ -73,7 +73,7 @@ export async function getModelSuggestions(provider: string, partialModel: string
 }
 
 export async function getLiteLLMModels(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/llm/models/litellm`;
+  const url = `${getBackendUrl()}/llm/models/litellm`;
   const response = await fetch(url, {
     method: "GET",
     headers: { "Content-Type": "application/json" },
@@ -87,7 +87,7 @@ export async function getLiteLLMModels(): Promise<any> {
 }
 
 export async function getLiteLLMProviderModels(provider: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/llm/models/litellm/${provider}`;
+  const url = `${getBackendUrl()}/llm/models/litellm/${provider}`;
   const response = await fetch(url, {
     method: "GET",
     headers: { "Content-Type": "application/json" },
@@ -101,7 +101,7 @@ export async function getLiteLLMProviderModels(provider: string): Promise<any> {
 }
 
 export async function getLLMConfig(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/llm/config`;
+  const url = `${getBackendUrl()}/llm/config`;
   const response = await fetch(url, {
     method: "GET",
     headers: { "Content-Type": "application/json" },
@@ -115,14 +115,14 @@ export async function getLLMConfig(): Promise<any> {
 }
 
 export async function getAvailableProviders(): Promise<{ providers: { id: string; name: string }[] }> {
-  const url = `${PYTHON_BACKEND_URL}/llm/providers`;
+  const url = `${getBackendUrl()}/llm/providers`;
   const response = await fetch(url);
   const data = await response.json();
   return data as unknown as { providers: { id: string; name: string }[] };
 }
 
 export async function setModelParams(params: ModelParamsRequest): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/llm/model/params`;
+  const url = `${getBackendUrl()}/llm/model/params`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -138,7 +138,7 @@ export async function setModelParams(params: ModelParamsRequest): Promise<any> {
 
 // ===== Agent Types Management =====
 export async function getAgentTypes(): Promise<string[]> {
-  const url = `${PYTHON_BACKEND_URL}/agents`;
+  const url = `${getBackendUrl()}/agents`;
   const response = await fetch(url);
   if (!response.ok) {
     throw new Error(`Failed to fetch agent types: ${response.statusText}`);
@@ -150,7 +150,7 @@ export async function getAgentTypes(): Promise<string[]> {
 // ===== Session Management =====
 
 export async function deleteSession(sessionId: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/sessions/${sessionId}`;
+  const url = `${getBackendUrl()}/sessions/${sessionId}`;
   const response = await fetch(url, {
     method: "DELETE",
   });
@@ -162,7 +162,7 @@ export async function sendMessage(
   message: string,
   agentType: string = "chat",
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/sessions/${sessionId}/message?agent_type=${agentType}`;
+  const url = `${getBackendUrl()}/sessions/${sessionId}/message?agent_type=${agentType}`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -183,7 +183,7 @@ export async function streamMessage(
     requestBody.context = context;
   }
 
-  const url = `${PYTHON_BACKEND_URL}/sessions/${sessionId}/stream?agent_type=${agentType}`;
+  const url = `${getBackendUrl()}/sessions/${sessionId}/stream?agent_type=${agentType}`;
 
   return fetch(url, {
     method: "POST",
@@ -221,7 +221,7 @@ export function convertBackendSessionsToMap(
 }
 
 export async function getSessionInfo(sessionId: string): Promise<SessionInfo> {
-  const url = `${PYTHON_BACKEND_URL}/sessions/${sessionId}`;
+  const url = `${getBackendUrl()}/sessions/${sessionId}`;
   const response = await fetch(url);
   const data = await response.json();
   return data as unknown as SessionInfo;
@@ -237,7 +237,7 @@ export async function getSessionMessages(
   has_more: boolean;
   oldest: number | null;
 }> {
-  const url = new URL(`${PYTHON_BACKEND_URL}/sessions/${sessionId}/messages`);
+  const url = new URL(`${getBackendUrl()}/sessions/${sessionId}/messages`);
   if (before !== undefined) {
     url.searchParams.set("before", String(before));
   }
@@ -251,7 +251,7 @@ export async function getSessionMessages(
 }
 
 export async function listSessions(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/sessions`;
+  const url = `${getBackendUrl()}/sessions`;
   const response = await fetch(url);
   return response.json();
 }
@@ -260,7 +260,7 @@ export async function updateSessionTitle(
   sessionId: string,
   title: string,
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/sessions/${sessionId}/title?title=${encodeURIComponent(title)}`;
+  const url = `${getBackendUrl()}/sessions/${sessionId}/title?title=${encodeURIComponent(title)}`;
   const response = await fetch(url, {
     method: "POST",
   });
@@ -271,7 +271,7 @@ export async function updateSessionTitle(
 
 export async function checkBackendHealth(): Promise<boolean> {
   try {
-    const url = `${PYTHON_BACKEND_URL}/health`;
+    const url = `${getBackendUrl()}/health`;
     const response = await fetch(url, {
       signal: AbortSignal.timeout(5000),
     });
@@ -282,7 +282,7 @@ export async function checkBackendHealth(): Promise<boolean> {
 }
 
 export async function getChatGraphHealth(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/chat-graph/health`;
+  const url = `${getBackendUrl()}/chat-graph/health`;
   const response = await fetch(url);
   return response.json();
 }
@@ -326,7 +326,7 @@ export async function getZoteroCollections(
   const endpoint = limit
     ? `/zotero/collections?limit=${limit}`
     : "/zotero/collections";
-  const url = `${PYTHON_BACKEND_URL}${endpoint}`;
+  const url = `${getBackendUrl()}${endpoint}`;
   const response = await fetch(url);
   const data = await response.json();
   return data as unknown as ZoteroCollectionsResponse;
@@ -335,7 +335,7 @@ export async function getZoteroCollections(
 export async function getZoteroCollectionItems(
   collectionId: string,
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/zotero/collections/${collectionId}/items`;
+  const url = `${getBackendUrl()}/zotero/collections/${collectionId}/items`;
   const response = await fetch(url);
   return response.json();
 }
@@ -345,7 +345,7 @@ export async function addZoteroItemsToKnowledgeBaseStream(
   zoteroKeys: string[],
   onProgress: (progress: any) => void,
 ) {
-  const url = `${PYTHON_BACKEND_URL}/rag/zotero/bulk-add-stream`;
+  const url = `${getBackendUrl()}/rag/zotero/bulk-add-stream`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -391,7 +391,7 @@ export async function getZoteroStatus(): Promise<{
   connected: boolean;
   error?: string;
 }> {
-  const url = `${PYTHON_BACKEND_URL}/zotero/status`;
+  const url = `${getBackendUrl()}/zotero/status`;
   const response = await fetch(url);
   const data = await response.json();
   return data as unknown as { connected: boolean; error?: string };
@@ -399,14 +399,14 @@ export async function getZoteroStatus(): Promise<{
 
 // System Status Functions
 export async function getSystemStatus(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/system/status`;
+  const url = `${getBackendUrl()}/system/status`;
   const response = await fetch(url);
   return response.json();
 }
 
 // Configuration Management Functions (Single User)
 export async function getSystemConfig(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/config/`;
+  const url = `${getBackendUrl()}/config/`;
   const response = await fetch(url);
   return response.json();
 }
@@ -440,7 +440,7 @@ export async function updateSystemConfig(updates: {
   chunk_overlap?: number;
   enable_reference_filtering?: boolean;
 }): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/config/`;
+  const url = `${getBackendUrl()}/config/`;
   const response = await fetch(url, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
@@ -450,7 +450,7 @@ export async function updateSystemConfig(updates: {
 }
 
 export async function createSystemBackup(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/system/backup`;
+  const url = `${getBackendUrl()}/system/backup`;
   const response = await fetch(url, {
     method: "POST",
   });
@@ -458,7 +458,7 @@ export async function createSystemBackup(): Promise<any> {
 }
 
 export async function reloadSystemConfig(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/config/reload`;
+  const url = `${getBackendUrl()}/config/reload`;
   const response = await fetch(url, {
     method: "POST",
   });
@@ -466,7 +466,7 @@ export async function reloadSystemConfig(): Promise<any> {
 }
 
 export async function getSystemHealth(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/system/health`;
+  const url = `${getBackendUrl()}/system/health`;
   const response = await fetch(url);
   return response.json();
 }
@@ -479,7 +479,7 @@ export async function getSystemHealth(): Promise<any> {
 
 // Reload backend configuration and environment variables
 export async function reloadBackendConfig(): Promise<void> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/config/reload`, {
+  const response = await fetch(`${getBackendUrl()}/config/reload`, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
   });
@@ -494,7 +494,7 @@ export async function reloadBackendConfig(): Promise<void> {
 
 // ===== Knowledge Base Functions =====
 export async function getKnowledgeBases(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/knowledge-bases`;
+  const url = `${getBackendUrl()}/rag/knowledge-bases`;
   const response = await fetch(url);
   return response.json();
 }
@@ -507,7 +507,7 @@ export async function createKnowledgeBase(data: {
   embed_model?: string;
   enable_reference_filtering?: boolean;
 }): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/knowledge-bases`;
+  const url = `${getBackendUrl()}/rag/knowledge-bases`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -540,7 +540,7 @@ export async function updateKnowledgeBase(
   kbId: string,
   data: any,
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/knowledge_bases/${kbId}`;
+  const url = `${getBackendUrl()}/rag/knowledge_bases/${kbId}`;
   const response = await fetch(url, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
@@ -554,7 +554,7 @@ export async function updateKnowledgeBase(
 }
 
 export async function getActiveKnowledgeBases(): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/active-knowledge-bases`;
+  const url = `${getBackendUrl()}/rag/active-knowledge-bases`;
   const response = await fetch(url, {
     method: "GET",
     headers: { "Content-Type": "application/json" },
@@ -569,7 +569,7 @@ export async function getActiveKnowledgeBases(): Promise<any> {
 export async function setActiveKnowledgeBases(
   knowledgeBaseIds: string[],
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/active-knowledge-bases`;
+  const url = `${getBackendUrl()}/rag/active-knowledge-bases`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -583,7 +583,7 @@ export async function setActiveKnowledgeBases(
 }
 
 export async function deleteKnowledgeBase(kbId: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/knowledge-bases/${kbId}`;
+  const url = `${getBackendUrl()}/rag/knowledge-bases/${kbId}`;
   const response = await fetch(url, {
     method: "DELETE",
   });
@@ -595,7 +595,7 @@ export async function deleteKnowledgeBase(kbId: string): Promise<any> {
 }
 
 export async function getKnowledgeBaseDocuments(kbId: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/knowledge-bases/${kbId}/documents`;
+  const url = `${getBackendUrl()}/rag/knowledge-bases/${kbId}/documents`;
   const response = await fetch(url);
   return response.json();
 }
@@ -605,7 +605,7 @@ export async function queryDocuments(data: {
   knowledge_base_names: string[];
   k?: number;
 }): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/query`;
+  const url = `${getBackendUrl()}/rag/documents/query`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -626,7 +626,7 @@ export async function addDocuments(data: {
   }>;
   knowledge_base_name: string;
 }): Promise<any> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/rag/documents/add`, {
+  const response = await fetch(`${getBackendUrl()}/rag/documents/add`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
@@ -643,7 +643,7 @@ export async function addDocuments(data: {
 
 // ===== File Upload Functions =====
 export async function uploadFile(formData: FormData): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/upload`;
+  const url = `${getBackendUrl()}/rag/documents/upload`;
   const response = await fetch(url, {
     method: "POST",
     body: formData,
@@ -672,7 +672,7 @@ export async function uploadFile(formData: FormData): Promise<any> {
 export async function uploadPdfToKnowledgeBase(
   formData: FormData,
 ): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/pdf`;
+  const url = `${getBackendUrl()}/rag/documents/pdf`;
   const response = await fetch(url, {
     method: "POST",
     body: formData,
@@ -702,7 +702,7 @@ export async function extractTextFromPdf(file: File): Promise<any> {
   const formData = new FormData();
   formData.append("file", file);
 
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/extract-text`;
+  const url = `${getBackendUrl()}/rag/documents/extract-text`;
   const response = await fetch(url, {
     method: "POST",
     body: formData,
@@ -729,7 +729,7 @@ export async function extractTextFromPdf(file: File): Promise<any> {
 }
 
 export async function getDocumentByKey(key: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/${key}`;
+  const url = `${getBackendUrl()}/rag/documents/${key}`;
   const response = await fetch(url);
   return response.json();
 }
@@ -742,7 +742,7 @@ export async function bulkAddFromZotero(data: {
   knowledge_base_name?: string;
   knowledgeBaseName?: string;
 }): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/zotero/bulk-add-stream`;
+  const url = `${getBackendUrl()}/rag/zotero/bulk-add-stream`;
   const response = await fetch(url, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
@@ -753,7 +753,7 @@ export async function bulkAddFromZotero(data: {
 
 // ===== Document Management =====
 export async function deleteDocument(id: string): Promise<any> {
-  const url = `${PYTHON_BACKEND_URL}/rag/documents/${id}`;
+  const url = `${getBackendUrl()}/rag/documents/${id}`;
   const response = await fetch(url, {
     method: "DELETE",
   });
@@ -765,7 +765,7 @@ export async function deleteDocument(id: string): Promise<any> {
 
 // ===== MCP Configuration Functions =====
 export async function getMCPConfig(): Promise<any> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/mcp/config`);
+  const response = await fetch(`${getBackendUrl()}/mcp/config`);
 
   if (!response.ok) {
     throw new Error(`HTTP error! status: ${response.status}`);
@@ -778,7 +778,7 @@ export async function updateMCPConfig(config: {
   transport?: string;
   port?: number;
 }): Promise<any> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/mcp/config`, {
+  const response = await fetch(`${getBackendUrl()}/mcp/config`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
@@ -794,7 +794,7 @@ export async function updateMCPConfig(config: {
 }
 
 export async function restartMCPServer(): Promise<any> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/mcp/restart`, {
+  const response = await fetch(`${getBackendUrl()}/mcp/restart`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
@@ -809,7 +809,7 @@ export async function restartMCPServer(): Promise<any> {
 }
 
 export async function getMCPStatus(): Promise<any> {
-  const response = await fetch(`${PYTHON_BACKEND_URL}/mcp/status`);
+  const response = await fetch(`${getBackendUrl()}/mcp/status`);
 
   if (!response.ok) {
     throw new Error(`HTTP error! status: ${response.status}`);
@@ -0,0 +1,24 @@
+/**
+ * Base URL of the Python backend.
+ *
+ * In the browser it comes from NEXT_PUBLIC_PYTHON_BACKEND_URL. In Tauri the
+ * sidecar falls back to a random port when 8009 is taken, and an external
+ * backend can live anywhere, so TauriService.syncBackendUrl replaces the
+ * default with what the app reports. Read it at request time rather than
+ * caching it in a constant.
+ */
+
+const DEFAULT_BACKEND_URL =
+  process.env.NEXT_PUBLIC_PYTHON_BACKEND_URL ||
+  process.env.NEXT_PUBLIC_BACKEND_URL ||
+  "http://localhost:8009";
+
+let backendUrl = DEFAULT_BACKEND_URL;
+
+export function getBackendUrl(): string {
+  return backendUrl;
+}
+
+export function setBackendUrl(url: string): void {
+  backendUrl = url.replace(/\/+$/, "");
+}
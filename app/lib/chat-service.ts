@@ -6,6 +6,7 @@
  */
 
 import { ChatMessage, ProviderConfig, ChatRequest } from "./utils";
+import { getBackendUrl } from "./backend-url";
 import { selectedModelAtom } from "@/store/chatAtoms";
 import { useAtom } from "jotai";
 import { useToast } from "@/hooks/use-toast";
@@ -56,12 +57,11 @@ export interface SaveConfigResult {
 }
 
 class ChatService {
-  private baseUrl: string;
   private abortController: AbortController | null = null;
 
-  constructor() {
-    this.baseUrl =
-      process.env.NEXT_PUBLIC_BACKEND_URL || "http://localhost:8009";
+  // Resolved per request, the port can change when the sidecar restarts
+  private get baseUrl(): string {
+    return getBackendUrl();
   }
 
   /**
@@ -203,7 +203,7 @@ class ChatService {
       await updateSystemConfig(updates);
       
       // Test by trying to fetch models (which tests connectivity)
-      const response = await fetch(`${process.env.NEXT_PUBLIC_PYTHON_BACKEND_URL || "http://localhost:8009"}/llm/models`);
+      const response = await fetch(`${getBackendUrl()}/llm/models`);
       const result = await response.json();
       
       // Fix error property access by casting result to any
@@ -1,6 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
+import { getBackendUrl } from "./backend-url";
 
-const PYTHON_BACKEND_URL = process.env.NEXT_PUBLIC_PYTHON_BACKEND_URL || "http://localhost:8009";
 
 function isTauri(): boolean {
   try {
@@ -16,7 +16,7 @@ function isTauri(): boolean {
 export async function getEnvVars(): Promise<Record<string, string>> {
   if (!isTauri()) return {};
   
-  const response = await fetch(`${PYTHON_BACKEND_URL}/config/env-vars/encrypted`, {
+  const response = await fetch(`${getBackendUrl()}/config/env-vars/encrypted`, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({}),
@@ -30,7 +30,7 @@ export async function getEnvVars(): Promise<Record<string, string>> {
 export async function setEnvVar(name: string, value: string): Promise<void> {
   if (!isTauri()) return;
   
-  const response = await fetch(`${PYTHON_BACKEND_URL}/config/env-vars/encrypted`, {
+  const response = await fetch(`${getBackendUrl()}/config/env-vars/encrypted`, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({ name, value }),
@@ -43,7 +43,7 @@ export async function setEnvVar(name: string, value: string): Promise<void> {
 export async function deleteEnvVar(name: string): Promise<void> {
   if (!isTauri()) return;
   
-  const response = await fetch(`${PYTHON_BACKEND_URL}/config/env-vars/encrypted`, {
+  const response = await fetch(`${getBackendUrl()}/config/env-vars/encrypted`, {
     method: "DELETE",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({ name }),
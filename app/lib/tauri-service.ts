@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { getBackendUrl, setBackendUrl } from "./backend-url";
 /**
  * Tauri Service for Sidecar Management
  *
@@ -12,11 +13,37 @@ export interface SidecarConfig {
   port: number;
 }
 
+/**
+ * What every Tauri command rejects with. `code` is stable (e.g.
+ * "sidecar_not_running"), `message` is meant for display.
+ */
+export interface ChikenError {
+  code: string;
+  message: string;
+}
+
+export function isChikenError(error: unknown): error is ChikenError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    typeof (error as ChikenError).code === "string" &&
+    typeof (error as ChikenError).message === "string"
+  );
+}
+
+export function errorMessage(error: unknown): string {
+  if (isChikenError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}
+
 export class TauriService {
   private static instance: TauriService;
   private isTauri: boolean = false;
-  private currentPort: number = 8009;
   private sidecarStarted: boolean = false;
+  private authInstalled: boolean = false;
+  private urlSync: Promise<void> | null = null;
 
   constructor() {
     // Check if we're running in Tauri
@@ -73,7 +100,7 @@ export class TauriService {
     if (!this.isTauri) {
       return {
         success: false,
-        port: 8009,
+        port: this.getCurrentPort(),
         message: "Not running in Tauri mode",
       };
     }
@@ -81,7 +108,7 @@ export class TauriService {
     if (this.sidecarStarted) {
       return {
         success: true,
-        port: this.currentPort,
+        port: this.getCurrentPort(),
         message: "Sidecar already started",
       };
     }
@@ -93,12 +120,12 @@ export class TauriService {
       const isReady = await this.waitForSidecarReady();
       if (isReady) {
         this.sidecarStarted = true;
-        return { success: true, port: this.currentPort, message: result };
+        return { success: true, port: this.getCurrentPort(), message: result };
       } else {
         throw new Error("Sidecar started but failed health check");
       }
     } catch (error) {
-      throw new Error(`Failed to start sidecar: ${error}`);
+      throw new Error(`Failed to start sidecar: ${errorMessage(error)}`);
     }
   }
 
@@ -111,13 +138,10 @@ export class TauriService {
   ): Promise<boolean> {
     for (let attempt = 1; attempt <= maxAttempts; attempt++) {
       try {
-        const response = await fetch(
-          `http://localhost:${this.currentPort}/health`,
-          {
-            method: "GET",
-            signal: AbortSignal.timeout(2000),
-          },
-        );
+        const response = await fetch(`${getBackendUrl()}/health`, {
+          method: "GET",
+          signal: AbortSignal.timeout(2000),
+        });
 
         if (response.ok) {
           console.log(`Sidecar health check passed on attempt ${attempt}`);
@@ -158,7 +182,7 @@ export class TauriService {
     } catch (error) {
       return {
         success: false,
-        message: `Failed to shutdown sidecar: ${error}`,
+        message: `Failed to shutdown sidecar: ${errorMessage(error)}`,
       };
     }
   }
@@ -172,13 +196,10 @@ export class TauriService {
     }
 
     try {
-      const response = await fetch(
-        `http://localhost:${this.currentPort}/health`,
-        {
-          method: "GET",
-          signal: AbortSignal.timeout(2000),
-        },
-      );
+      const response = await fetch(`${getBackendUrl()}/health`, {
+        method: "GET",
+        signal: AbortSignal.timeout(2000),
+      });
       const isRunning = response.ok;
       this.sidecarStarted = isRunning;
       return isRunning;
@@ -189,7 +210,7 @@ export class TauriService {
   }
 
   /**
-   * Listen to sidecar stdout events
+   * Listen to sidecar stdout, delivered in batches of lines
    */
   async listenToSidecarStdout(
     callback: (data: string) => void,
@@ -199,8 +220,8 @@ export class TauriService {
     }
 
     try {
-      const unlisten = await listen("sidecar-stdout", (event) => {
-        callback(event.payload as string);
+      const unlisten = await listen<string[]>("sidecar-stdout-batch", (event) => {
+        event.payload.forEach(callback);
       });
       return unlisten;
     } catch (error) {
@@ -229,22 +250,61 @@ export class TauriService {
   }
 
   /**
-   * Get the current backend URL
+   * Attach the backend auth token to every fetch aimed at the local backend.
+   * The sidecar rejects requests without it, so call this before talking to it.
    */
-  getBackendUrl(): string {
-    if (this.isTauri) {
-      return `http://localhost:${this.currentPort}`;
+  async installBackendAuth(): Promise<void> {
+    if (!this.isTauri || this.authInstalled) return;
+    const token = await invoke<string | null>("get_backend_token");
+    if (!token) return;
+    const originalFetch = globalThis.fetch.bind(globalThis);
+    globalThis.fetch = (input: RequestInfo | URL, init?: RequestInit) => {
+      const url = input instanceof Request ? input.url : input.toString();
+      if (!/^https?:\/\/(localhost|127\.0\.0\.1)(:\d+)?\//.test(url)) {
+        return originalFetch(input, init);
+      }
+      const headers = new Headers(init?.headers ?? (input instanceof Request ? input.headers : undefined));
+      headers.set("X-Chiken-Token", token);
+      return originalFetch(input, { ...init, headers });
+    };
+    this.authInstalled = true;
+  }
+
+  /**
+   * Point every backend request at the URL the app reports. It differs from the
+   * 8009 default when that port was taken or an external backend is configured.
+   * Resolves once the backend is up; later changes (a restart on another port)
+   * arrive through backend-url-changed.
+   */
+  syncBackendUrl(): Promise<void> {
+    if (!this.isTauri) return Promise.resolve();
+    if (!this.urlSync) {
+      this.urlSync = (async () => {
+        await listen<string>("backend-url-changed", (event) => setBackendUrl(event.payload));
+        const { url } = await invoke<{ url: string; token: string | null }>("wait_for_backend");
+        setBackendUrl(url);
+      })().catch((error) => {
+        // Let the next caller try again
+        this.urlSync = null;
+        throw error;
+      });
     }
+    return this.urlSync;
+  }
 
-    // Default for web mode
-    return process.env.NEXT_PUBLIC_BACKEND_URL || "http://localhost:8009";
+  /**
+   * Get the current backend URL
+   */
+  getBackendUrl(): string {
+    return getBackendUrl();
   }
 
   /**
    * Get the current port being used by the sidecar
    */
   getCurrentPort(): number {
-    return this.currentPort;
+    const url = new URL(getBackendUrl());
+    return Number(url.port) || (url.protocol === "https:" ? 443 : 80);
   }
 }
 
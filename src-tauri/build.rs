@@ -1,3 +1,53 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+    // Build metadata reported by get_app_info
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    println!("cargo:rustc-env=CHIKEN_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=CHIKEN_BUILD_TIMESTAMP={}", build_timestamp);
+    println!(
+        "cargo:rustc-env=CHIKEN_BUILD_DATE={}",
+        utc_date(build_timestamp / 86400)
+    );
+    // Any rerun-if-changed turns off cargo's default of rerunning on every
+    // change in the package, so src/ is listed to keep the timestamp current.
+    // HEAD alone only changes on checkout; a commit moves the branch ref it
+    // points to, which lives either in its own file or in packed-refs.
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/packed-refs");
+    if let Some(head_ref) = std::fs::read_to_string("../.git/HEAD")
+        .ok()
+        .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        println!("cargo:rerun-if-changed=../.git/{}", head_ref);
+    }
+
     tauri_build::build()
 }
+
+// YYYY-MM-DD for a count of days since the Unix epoch (Howard Hinnant's
+// civil_from_days), to avoid a date crate as a build dependency
+fn utc_date(days: u64) -> String {
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
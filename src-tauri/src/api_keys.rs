@@ -0,0 +1,323 @@
+use crate::error::ChikenError;
+use regex::Regex;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::time::Duration;
+use tauri::Url;
+use tauri_plugin_http::reqwest::{self, StatusCode};
+
+// How long test_api_key and verify_api_key wait for the provider
+const KEY_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Known API key formats per provider. A key stored through set_validated_secret
+// lands under the provider's env var name, so the sidecar gets it as-is.
+struct ProviderKey {
+    provider: &'static str,
+    secret_key: &'static str,
+    // What every key for the provider starts with; also used to spot a key
+    // pasted into the wrong provider's field
+    prefix: &'static str,
+    pattern: &'static str,
+    // Cheapest authenticated GET the provider has, used to test a key. The path
+    // is relative to base_url, which verify_api_key can override for
+    // OpenAI-compatible servers.
+    base_url: &'static str,
+    check_path: &'static str,
+    auth: KeyAuth,
+}
+
+// How the key is sent with the check request
+enum KeyAuth {
+    Bearer,
+    // Anthropic's x-api-key, which also requires an API version header
+    Anthropic,
+    // Google's x-goog-api-key
+    Google,
+}
+
+const PROVIDERS: &[ProviderKey] = &[
+    ProviderKey {
+        provider: "openai",
+        secret_key: "OPENAI_API_KEY",
+        prefix: "sk-",
+        pattern: r"^sk-[A-Za-z0-9_-]{20,}$",
+        base_url: "https://api.openai.com/v1",
+        check_path: "/models",
+        auth: KeyAuth::Bearer,
+    },
+    ProviderKey {
+        provider: "anthropic",
+        secret_key: "ANTHROPIC_API_KEY",
+        prefix: "sk-ant-",
+        pattern: r"^sk-ant-[A-Za-z0-9_-]{20,}$",
+        base_url: "https://api.anthropic.com/v1",
+        check_path: "/models",
+        auth: KeyAuth::Anthropic,
+    },
+    ProviderKey {
+        provider: "openrouter",
+        secret_key: "OPENROUTER_API_KEY",
+        prefix: "sk-or-",
+        pattern: r"^sk-or-[A-Za-z0-9_-]{20,}$",
+        base_url: "https://openrouter.ai/api/v1",
+        check_path: "/key",
+        auth: KeyAuth::Bearer,
+    },
+    ProviderKey {
+        provider: "huggingface",
+        secret_key: "HF_TOKEN",
+        prefix: "hf_",
+        pattern: r"^hf_[A-Za-z0-9]{20,}$",
+        base_url: "https://huggingface.co/api",
+        check_path: "/whoami-v2",
+        auth: KeyAuth::Bearer,
+    },
+    ProviderKey {
+        provider: "gemini",
+        secret_key: "GEMINI_API_KEY",
+        prefix: "AIza",
+        pattern: r"^AIza[A-Za-z0-9_-]{35}$",
+        base_url: "https://generativelanguage.googleapis.com/v1beta",
+        check_path: "/models",
+        auth: KeyAuth::Google,
+    },
+];
+
+fn provider_key(provider: &str) -> Result<&'static ProviderKey, ChikenError> {
+    PROVIDERS
+        .iter()
+        .find(|spec| spec.provider.eq_ignore_ascii_case(provider))
+        .ok_or_else(|| {
+            let known: Vec<&str> = PROVIDERS.iter().map(|spec| spec.provider).collect();
+            format!(
+                "No key format is known for provider '{}' (known: {}); store it with set_secret instead",
+                provider,
+                known.join(", ")
+            )
+            .into()
+        })
+}
+
+// Check `value` against the provider's key format, returning the trimmed key.
+// Whitespace is dropped since keys are usually pasted, often with a newline.
+fn validate<'a>(spec: &ProviderKey, value: &'a str) -> Result<&'a str, ChikenError> {
+    let value = value.trim();
+    // An sk-ant- key would pass the OpenAI sk- pattern
+    if let Some(other) = PROVIDERS
+        .iter()
+        .find(|other| other.prefix.len() > spec.prefix.len() && value.starts_with(other.prefix))
+    {
+        return Err(ChikenError::InvalidApiKey(format!(
+            "This looks like a {} key ({}...), not a {} key",
+            other.provider, other.prefix, spec.provider
+        )));
+    }
+    let pattern = Regex::new(spec.pattern).map_err(|e| e.to_string())?;
+    if !pattern.is_match(value) {
+        return Err(ChikenError::InvalidApiKey(format!(
+            "Not a valid {} key: expected {}... with no spaces; check that the whole key was copied",
+            spec.provider, spec.prefix
+        )));
+    }
+    Ok(value)
+}
+
+// Store a provider API key after checking its format, so a truncated or
+// misplaced key fails here instead of as an opaque "invalid key" from the
+// provider. Returns the secret key it was stored under.
+pub fn set_validated_secret(provider: &str, value: &str) -> Result<&'static str, ChikenError> {
+    let spec = provider_key(provider)?;
+    let value = validate(spec, value)?;
+    crate::secret_store::set_secret(spec.secret_key, value)?;
+    Ok(spec.secret_key)
+}
+
+// Send the provider's check request with `key`, returning the status it
+// answered with, or Network when there was no answer. The key is never logged.
+async fn check_key(
+    spec: &ProviderKey,
+    key: &str,
+    base_url: Option<&str>,
+) -> Result<StatusCode, ChikenError> {
+    let base_url = base_url.unwrap_or(spec.base_url).trim_end_matches('/');
+    let request = reqwest::Client::new()
+        .get(format!("{}{}", base_url, spec.check_path))
+        .timeout(KEY_CHECK_TIMEOUT);
+    let request = match spec.auth {
+        KeyAuth::Bearer => request.bearer_auth(key),
+        KeyAuth::Anthropic => request
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01"),
+        KeyAuth::Google => request.header("x-goog-api-key", key),
+    };
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ChikenError::Network(format!("Could not reach {}: {}", base_url, e)))?;
+    Ok(response.status())
+}
+
+fn check_message(spec: &ProviderKey, status: StatusCode) -> String {
+    if status.is_success() {
+        format!("{} accepted the key", spec.provider)
+    } else if is_rejected(status) {
+        format!(
+            "{} rejected the key (HTTP {}); check that it is complete and still active",
+            spec.provider,
+            status.as_u16()
+        )
+    } else {
+        format!(
+            "{} answered HTTP {} while checking the key",
+            spec.provider,
+            status.as_u16()
+        )
+    }
+}
+
+fn is_rejected(status: StatusCode) -> bool {
+    // Gemini answers an invalid key with 400 rather than 401
+    matches!(
+        status,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::BAD_REQUEST
+    )
+}
+
+// Check a pasted key with the provider: Ok(true) when it accepts the key,
+// InvalidApiKey when it rejects it, Network when it can't be reached. The key is
+// trimmed like a pasted one.
+pub async fn test_api_key(provider: &str, key: &str) -> Result<bool, ChikenError> {
+    let spec = provider_key(provider)?;
+    match check_key(spec, key.trim(), None).await? {
+        status if status.is_success() => Ok(true),
+        status if is_rejected(status) => {
+            Err(ChikenError::InvalidApiKey(check_message(spec, status)))
+        }
+        status => Err(check_message(spec, status).into()),
+    }
+}
+
+// Result of verify_api_key. Network failures are an Err(Network) instead, so
+// `valid: false` always means the provider answered.
+#[derive(Serialize)]
+pub struct KeyVerification {
+    pub valid: bool,
+    pub status: u16,
+    pub message: String,
+    // The stored key has surrounding whitespace; the check used the trimmed key,
+    // and the UI can offer to store that instead
+    pub trimmed: bool,
+}
+
+// A base_url override receives the stored key, so it has to be https, or plain
+// http on this machine for a local OpenAI-compatible server. Anything else
+// would send the key in the clear or to a host the user can't see in the URL.
+fn check_base_url(base_url: &str) -> Result<(), ChikenError> {
+    let url = Url::parse(base_url.trim())
+        .map_err(|e| format!("Not a valid base URL '{}': {}", base_url, e))?;
+    // IPv6 hosts come back bracketed, e.g. [::1]
+    let host = url
+        .host_str()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'));
+    let loopback = host.is_some_and(|host| {
+        host.eq_ignore_ascii_case("localhost")
+            || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+    });
+    match url.scheme() {
+        "https" if host.is_some() => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => Err(ChikenError::AccessDenied(format!(
+            "Refusing to send the key to {}: use an https:// URL, or http:// on localhost",
+            base_url
+        ))),
+    }
+}
+
+// Check the key stored for `provider` against the provider, or against
+// `base_url` for an OpenAI-compatible server
+pub async fn verify_api_key(
+    provider: &str,
+    base_url: Option<&str>,
+) -> Result<KeyVerification, ChikenError> {
+    let spec = provider_key(provider)?;
+    if let Some(base_url) = base_url {
+        check_base_url(base_url)?;
+    }
+    let stored = crate::secret_store::get_secret(spec.secret_key)?
+        .ok_or_else(|| ChikenError::NotFound(format!("No {} key is stored", spec.provider)))?;
+    let key = stored.trim();
+    let trimmed = key.len() != stored.len();
+    let status = check_key(spec, key, base_url).await?;
+    Ok(KeyVerification {
+        valid: status.is_success(),
+        status: status.as_u16(),
+        message: check_message(spec, status),
+        trimmed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn https_and_loopback_base_urls_are_allowed() {
+        for url in [
+            "https://api.openai.com/v1",
+            "https://llm.example.com:8443/v1/",
+            "http://localhost:11434/v1",
+            "http://LOCALHOST:1234",
+            "http://127.0.0.1:8080/v1",
+            "http://127.1.2.3/v1",
+            "http://[::1]:8000/v1",
+        ] {
+            assert!(check_base_url(url).is_ok(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn other_base_urls_are_refused() {
+        for url in [
+            "http://api.openai.com/v1",
+            "http://192.168.1.10:8000/v1",
+            "http://localhost.example.com/v1",
+            "ftp://localhost/v1",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                matches!(check_base_url(url), Err(ChikenError::AccessDenied(_))),
+                "{}",
+                url
+            );
+        }
+        assert!(matches!(
+            check_base_url("not a url"),
+            Err(ChikenError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn provider_defaults_pass_the_base_url_check() {
+        for spec in PROVIDERS {
+            assert!(check_base_url(spec.base_url).is_ok(), "{}", spec.base_url);
+        }
+    }
+
+    #[test]
+    fn key_for_another_provider_is_spotted() {
+        let spec = provider_key("openai").unwrap();
+        let key = format!("sk-ant-{}", "a".repeat(30));
+        let Err(ChikenError::InvalidApiKey(message)) = validate(spec, &key) else {
+            panic!("an Anthropic key should not pass as an OpenAI key");
+        };
+        assert!(message.contains("anthropic"), "{}", message);
+    }
+
+    #[test]
+    fn pasted_key_is_trimmed() {
+        let spec = provider_key("OpenAI").unwrap();
+        let key = format!("sk-{}", "a".repeat(30));
+        assert_eq!(validate(spec, &format!("  {}\n", key)).unwrap(), key);
+        assert!(validate(spec, &key[..10]).is_err());
+    }
+}
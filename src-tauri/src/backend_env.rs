@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+// Settings key holding the user's extra sidecar env vars as a {name: value} map,
+// e.g. HTTPS_PROXY, HF_HOME or CUDA_VISIBLE_DEVICES
+const SETTINGS_KEY: &str = "backend.env";
+// Set by the app on every spawn. PYTHONIOENCODING keeps the stdout pipe UTF-8,
+// which the monitor relies on to decode lines; the others are wiring the app
+// and the backend have to agree on.
+const RESERVED: [&str; 7] = [
+    "PYTHONIOENCODING",
+    "CHIKEN_LOG_LEVEL",
+    "CHIKEN_PORT",
+    "CHIKEN_DATA_DIR",
+    "CHIKEN_PROFILE",
+    "CHIKEN_AUTH_TOKEN",
+    "CHIKEN_DEVICE_ID",
+];
+
+fn is_reserved(name: &str) -> bool {
+    RESERVED
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
+pub fn list(app: &AppHandle) -> BTreeMap<String, String> {
+    crate::settings::get(app, SETTINGS_KEY).unwrap_or_default()
+}
+
+// Entries to apply to the sidecar command. Reserved and malformed names are
+// dropped in case the store was edited by hand.
+pub fn sidecar_env(app: &AppHandle) -> Vec<(String, String)> {
+    list(app)
+        .into_iter()
+        .filter(|(name, _)| {
+            let usable = crate::secret_store::is_env_name(name) && !is_reserved(name);
+            if !usable {
+                tracing::warn!("Ignoring backend.env entry {}", name);
+            }
+            usable
+        })
+        .collect()
+}
+
+// Store a variable, or remove it when value is None. Takes effect on the next
+// sidecar spawn.
+pub fn set(app: &AppHandle, name: &str, value: Option<String>) -> Result<(), String> {
+    let name = name.trim();
+    if !crate::secret_store::is_env_name(name) {
+        return Err(format!("{} is not a valid environment variable name", name));
+    }
+    if is_reserved(name) {
+        return Err(format!(
+            "{} is set by the app and can't be overridden",
+            name
+        ));
+    }
+    let mut env = list(app);
+    match value {
+        Some(value) => env.insert(name.to_string(), value),
+        None => env.remove(name),
+    };
+    let env = serde_json::to_value(env).map_err(|e| e.to_string())?;
+    crate::settings::set(app, SETTINGS_KEY, env)
+}
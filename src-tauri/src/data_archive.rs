@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_store::StoreExt;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const FORMAT_VERSION: u32 = 1;
+// Top-level folders of the archive: the app data dir (settings store,
+// knowledge base, database) and the sidecar logs
+const APP_ROOT: &str = "app";
+// Archives from before the backend was given the app data dir keep the
+// backend's files here; they are restored into the app data dir
+const LEGACY_BACKEND_ROOT: &str = "backend";
+const LOGS_ROOT: &str = "logs";
+// Machine-bound or per-run files that must not travel to another install.
+// secrets.enc can only be decrypted on the machine that wrote it.
+const SKIPPED_FILES: [&str; 2] = [crate::secret_file::FILE_NAME, "sidecar.pid"];
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    app_version: String,
+    created: u64,
+    // Names only. Secret values stay in the keyring and have to be entered
+    // again after importing on a new machine.
+    secret_keys: Vec<String>,
+}
+
+// Where get_app_data_directory() in src/backends/constants.py put packaged
+// builds' data before the sidecar was given CHIKEN_DATA_DIR
+fn legacy_backend_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let path = app_handle.path();
+    let dir = if cfg!(any(target_os = "macos", windows)) {
+        path.data_dir()
+    } else {
+        path.home_dir()
+            .map(|home| home.join(".local").join("share"))
+    };
+    dir.map(|dir| dir.join("ChiKen"))
+        .map_err(|e| format!("Failed to resolve backend data dir: {}", e))
+}
+
+// Move a packaged backend's data from the legacy dir into `data_dir`. Entries
+// already present in `data_dir` are left where they are, and the legacy dir is
+// removed once empty. Development runs kept data in the project root, which is
+// never touched, and neither is it for a --profile, which starts out empty.
+pub fn migrate_legacy_backend_data(app_handle: &tauri::AppHandle, data_dir: &Path) {
+    if cfg!(debug_assertions) || crate::profile::current().is_some() {
+        return;
+    }
+    let Ok(legacy) = legacy_backend_data_dir(app_handle) else {
+        return;
+    };
+    if legacy == data_dir {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(&legacy) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let target = data_dir.join(entry.file_name());
+        if target.exists() {
+            tracing::warn!(
+                "Not migrating {}: {} already exists",
+                entry.path().display(),
+                target.display()
+            );
+            continue;
+        }
+        match fs::rename(entry.path(), &target) {
+            Ok(()) => tracing::info!(
+                "Migrated {} to {}",
+                entry.path().display(),
+                target.display()
+            ),
+            Err(e) => tracing::warn!("Failed to migrate {}: {}", entry.path().display(), e),
+        }
+    }
+    // Only succeeds once everything has moved
+    fs::remove_dir(&legacy).ok();
+}
+
+fn roots(app_handle: &tauri::AppHandle) -> Result<Vec<(&'static str, PathBuf)>, String> {
+    let path = app_handle.path();
+    Ok(vec![
+        (
+            APP_ROOT,
+            path.app_data_dir()
+                .map_err(|e| format!("Failed to resolve data dir: {}", e))?,
+        ),
+        (
+            LOGS_ROOT,
+            path.app_log_dir()
+                .map_err(|e| format!("Failed to resolve log directory: {}", e))?,
+        ),
+    ])
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// Zip all app data into `dest`. Files are streamed into the archive one at a
+// time, so a large knowledge base never has to fit in memory. The backend keeps
+// running; anything it writes during the export may be missing from the copy.
+pub fn export(app_handle: &tauri::AppHandle, dest: &Path) -> Result<usize, String> {
+    let roots = roots(app_handle)?;
+    let file =
+        File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: app_handle.package_info().version.to_string(),
+        created: unix_now(),
+        secret_keys: crate::secret_store::list_secret_keys().unwrap_or_else(|e| {
+            tracing::warn!("Exporting without the list of secret keys: {}", e);
+            Vec::new()
+        }),
+    };
+    zip.start_file(MANIFEST_NAME, SimpleFileOptions::default())
+        .map_err(|e| format!("Failed to write the manifest: {}", e))?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)
+        .map_err(|e| format!("Failed to write the manifest: {}", e))?;
+
+    let mut files = 0;
+    for (name, dir) in &roots {
+        // Nested roots (on Linux the log dir sits inside the app data dir) are
+        // archived only under their own name
+        let nested: Vec<&Path> = roots
+            .iter()
+            .filter(|(other, other_dir)| other != name && other_dir.starts_with(dir))
+            .map(|(_, other_dir)| other_dir.as_path())
+            .collect();
+        if dir.is_dir() {
+            files += add_dir(&mut zip, dir, dir, name, &nested)?;
+        }
+    }
+    zip.finish()
+        .and_then(|mut writer| writer.flush().map_err(Into::into))
+        .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    tracing::info!("Exported {} file(s) to {}", files, dest.display());
+    Ok(files)
+}
+
+fn add_dir<W: Write + io::Seek>(
+    zip: &mut ZipWriter<W>,
+    root: &Path,
+    dir: &Path,
+    prefix: &str,
+    skipped_dirs: &[&Path],
+) -> Result<usize, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files = 0;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let path = entry.path();
+        // Symlinks are skipped rather than followed out of the data dir
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !skipped_dirs.contains(&path.as_path()) {
+                files += add_dir(zip, root, &path, prefix, skipped_dirs)?;
+            }
+            continue;
+        }
+        if !file_type.is_file()
+            || SKIPPED_FILES
+                .iter()
+                .any(|skipped| entry.file_name() == std::ffi::OsStr::new(skipped))
+        {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        // Zip entry names always use forward slashes
+        let name = std::iter::once(prefix.to_string())
+            .chain(
+                relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy().to_string()),
+            )
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut file =
+            File::open(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let options = SimpleFileOptions::default().large_file(size >= u32::MAX as u64);
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        io::copy(&mut file, zip)
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        files += 1;
+    }
+    Ok(files)
+}
+
+fn read_manifest<R: io::Read + io::Seek>(archive: &mut ZipArchive<R>) -> Result<Manifest, String> {
+    let entry = archive
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| "This is not a ChiKen data export (no manifest.json)".to_string())?;
+    let manifest: Manifest = serde_json::from_reader(entry)
+        .map_err(|e| format!("Invalid manifest.json in the export: {}", e))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "The export was made by a newer ChiKen ({}); update the app to import it",
+            manifest.app_version
+        ));
+    }
+    Ok(manifest)
+}
+
+// Replace the app's data with the contents of an export, after the user
+// confirms. The sidecar is stopped for the duration since it holds the
+// database open. The current data is not deleted but moved aside to
+// "<dir>.before-import-<unix time>". Logs from the export are not restored.
+// Returns false if the user declined.
+pub async fn import(app_handle: tauri::AppHandle, src: PathBuf) -> Result<bool, String> {
+    let file = File::open(&src).map_err(|e| format!("Failed to open {}: {}", src.display(), e))?;
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+    let manifest = read_manifest(&mut archive)?;
+
+    let dialog_handle = app_handle.clone();
+    let confirmed = tauri::async_runtime::spawn_blocking(move || {
+        dialog_handle
+            .dialog()
+            .message(
+                "Importing replaces your current knowledge bases and settings. \
+                 The current data is kept in a backup folder next to it.",
+            )
+            .title("Import ChiKen data?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Import".to_string(),
+                "Cancel".to_string(),
+            ))
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("Confirmation dialog failed: {}", e))?;
+    if !confirmed {
+        return Ok(false);
+    }
+
+    let state = app_handle
+        .try_state::<crate::SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+    crate::stop_sidecar_gracefully(&state, crate::shutdown_timeout(&app_handle)).await?;
+
+    let restore_handle = app_handle.clone();
+    let restored = tauri::async_runtime::spawn_blocking(move || restore(&restore_handle, archive))
+        .await
+        .map_err(|e| format!("Import failed: {}", e))
+        .and_then(|restored| restored);
+
+    // The store plugin caches settings.json in memory; pick up the imported file
+    if let Ok(store) = app_handle.store("settings.json") {
+        if let Err(e) = store.reload() {
+            tracing::warn!("Failed to reload settings after import: {}", e);
+        }
+    }
+    // Bring the backend back either way, on the imported data if that worked
+    if let Err(e) = crate::spawn_and_monitor_sidecar(app_handle.clone()) {
+        tracing::warn!("Failed to restart sidecar after import: {}", e);
+    }
+    let files = restored?;
+    tracing::info!(
+        "Imported {} file(s) from {} (exported by {})",
+        files,
+        src.display(),
+        manifest.app_version
+    );
+    app_handle.emit("data-imported", manifest.secret_keys).ok();
+    Ok(true)
+}
+
+fn restore<R: io::Read + io::Seek>(
+    app_handle: &tauri::AppHandle,
+    mut archive: ZipArchive<R>,
+) -> Result<usize, String> {
+    let roots: Vec<(&str, PathBuf)> = roots(app_handle)?
+        .into_iter()
+        .filter(|(name, _)| *name != LOGS_ROOT)
+        .collect();
+    let app_dir = roots
+        .iter()
+        .find(|(name, _)| *name == APP_ROOT)
+        .map(|(_, dir)| dir.clone())
+        .ok_or("App data dir not resolved")?;
+
+    // Move every existing dir aside first, so a failure can't leave half of the
+    // old data mixed with half of the new
+    let suffix = format!("before-import-{}", unix_now());
+    let mut moved = Vec::new();
+    for (_, dir) in &roots {
+        if !dir.exists() {
+            continue;
+        }
+        let mut backup = dir.as_os_str().to_owned();
+        backup.push(format!(".{}", suffix));
+        let backup = PathBuf::from(backup);
+        if let Err(e) = fs::rename(dir, &backup) {
+            for (dir, backup) in moved.iter().rev() {
+                fs::rename(backup, dir).ok();
+            }
+            return Err(format!("Failed to move {} aside: {}", dir.display(), e));
+        }
+        tracing::info!("Moved {} to {}", dir.display(), backup.display());
+        moved.push((dir.clone(), backup));
+    }
+    // The fallback secrets file isn't exported; keep this machine's one
+    for (dir, backup) in &moved {
+        let secrets = backup.join(crate::secret_file::FILE_NAME);
+        if secrets.exists() {
+            fs::create_dir_all(dir)
+                .and_then(|()| fs::copy(&secrets, dir.join(crate::secret_file::FILE_NAME)))
+                .map_err(|e| format!("Failed to keep {}: {}", secrets.display(), e))?;
+        }
+    }
+
+    let mut files = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read the export: {}", e))?;
+        // enclosed_name rejects absolute paths and ".." so nothing is written
+        // outside the target dirs
+        let Some(name) = entry.enclosed_name() else {
+            tracing::warn!("Skipping unsafe path in export: {}", entry.name());
+            continue;
+        };
+        let mut components = name.components();
+        let Some(root) = components.next() else {
+            continue;
+        };
+        let dir = if root.as_os_str() == std::ffi::OsStr::new(LEGACY_BACKEND_ROOT) {
+            &app_dir
+        } else {
+            let Some((_, dir)) = roots
+                .iter()
+                .find(|(root_name, _)| root.as_os_str() == std::ffi::OsStr::new(root_name))
+            else {
+                continue;
+            };
+            dir
+        };
+        let target = dir.join(components.as_path());
+        if entry.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut out = File::create(&target)
+            .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+        io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to restore {}: {}", target.display(), e))?;
+        files += 1;
+    }
+    Ok(files)
+}
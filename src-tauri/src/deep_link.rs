@@ -0,0 +1,149 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager, Url};
+
+pub const SCHEME: &str = "chiken";
+
+// Payload of the deep-link event. `segments` are the percent-decoded parts
+// after the scheme: chiken://session/abc123 gives ["session", "abc123"] and
+// chiken://kb/mykb/doc/42 gives ["kb", "mykb", "doc", "42"].
+#[derive(Clone, Serialize)]
+pub struct DeepLink {
+    pub url: String,
+    pub segments: Vec<String>,
+}
+
+// Payload of the deep-link-error event, for the UI to show instead of
+// silently dropping the link
+#[derive(Clone, Serialize)]
+pub struct DeepLinkError {
+    pub url: String,
+    pub message: String,
+}
+
+// Links are held back until the frontend calls deep_link_ready; one that
+// arrives on a cold start would otherwise be emitted before anything listens
+#[derive(Default)]
+pub struct DeepLinks(Mutex<Queue>);
+
+#[derive(Default)]
+struct Queue {
+    ready: bool,
+    pending: Vec<Result<DeepLink, DeepLinkError>>,
+}
+
+impl DeepLinks {
+    // Called once the frontend's listeners are registered; flushes the backlog
+    pub fn ready(&self, app_handle: &tauri::AppHandle) {
+        let pending = {
+            let mut queue = self.0.lock().unwrap();
+            queue.ready = true;
+            std::mem::take(&mut queue.pending)
+        };
+        for link in pending {
+            emit(app_handle, link);
+        }
+    }
+}
+
+fn emit(app_handle: &tauri::AppHandle, link: Result<DeepLink, DeepLinkError>) {
+    match link {
+        Ok(link) => app_handle.emit("deep-link", link),
+        Err(error) => {
+            tracing::warn!("Rejected deep link {}: {}", error.url, error.message);
+            app_handle.emit("deep-link-error", error)
+        }
+    }
+    .ok();
+}
+
+// Deep links passed on the command line, which is how they arrive on Windows and
+// Linux both on a cold start and through a second instance
+pub fn from_args<'a>(args: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    args.into_iter()
+        .filter(|arg| {
+            arg.get(..SCHEME.len() + 1)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn handle(app_handle: &tauri::AppHandle, urls: Vec<String>) {
+    if urls.is_empty() {
+        return;
+    }
+    let Some(links) = app_handle.try_state::<DeepLinks>() else {
+        return;
+    };
+    let mut queue = links.0.lock().unwrap();
+    // On a cold start the splash stays up; the link is delivered once the
+    // frontend is ready
+    if queue.ready {
+        crate::focus_main_window(app_handle);
+    }
+    for url in urls {
+        let link = parse(&url).map_err(|message| DeepLinkError {
+            url: url.clone(),
+            message,
+        });
+        if queue.ready {
+            emit(app_handle, link);
+        } else {
+            queue.pending.push(link);
+        }
+    }
+}
+
+fn parse(raw: &str) -> Result<DeepLink, String> {
+    let url = Url::parse(raw.trim()).map_err(|e| format!("Not a valid link: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link", SCHEME));
+    }
+    // chiken://session/abc parses with "session" as the host
+    let segments = url
+        .host_str()
+        .into_iter()
+        .chain(url.path_segments().into_iter().flatten())
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Result<Vec<_>, _>>()?;
+    let valid = matches!(
+        segments
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        ["session", _] | ["kb", _] | ["kb", _, "doc", _]
+    );
+    if !valid {
+        return Err(
+            "Unknown link; expected chiken://session/<id> or chiken://kb/<name>/doc/<id>"
+                .to_string(),
+        );
+    }
+    Ok(DeepLink {
+        url: url.to_string(),
+        segments,
+    })
+}
+
+fn percent_decode(segment: &str) -> Result<String, String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = segment
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid escape in link segment {}", segment))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("Link segment {} is not valid UTF-8", segment))
+}
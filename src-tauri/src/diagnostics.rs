@@ -0,0 +1,164 @@
+use crate::redact::{self, Redactor};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+// How much of the end of sidecar.log goes into the bundle
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+// Settings whose key contains one of these are blanked out entirely
+const SECRET_KEY_HINTS: [&str; 5] = ["key", "token", "secret", "password", "auth"];
+
+// Ask where to save, then write a zip with the recent sidecar log, sidecar status,
+// app info, how the last sidecar exited, the data dir and its size, and the
+// settings store with secret-looking values redacted. Keyring contents are never
+// read for the bundle; stored secret values are only used to scrub them out.
+// Returns None if the save dialog was cancelled.
+pub async fn export(app_handle: tauri::AppHandle) -> Result<Option<PathBuf>, String> {
+    let dialog_handle = app_handle.clone();
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        dialog_handle
+            .dialog()
+            .file()
+            .set_file_name("chiken-diagnostics.zip")
+            .add_filter("Zip archive", &["zip"])
+            .blocking_save_file()
+    })
+    .await
+    .map_err(|e| format!("Save dialog failed: {}", e))?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| format!("Unsupported save location: {}", e))?;
+
+    let mut secrets = crate::secret_store::secret_values().unwrap_or_default();
+    secrets.push(crate::backend_token().to_string());
+    let redactor = Redactor::new(secrets);
+    let log = redactor.redact(&log_tail(&app_handle)).into_owned();
+    let status = match crate::sidecar_status(app_handle.clone()) {
+        Ok(status) => to_value(&status),
+        Err(error) => serde_json::json!({ "error": error }),
+    };
+    let app_info = to_value(&crate::get_app_info(app_handle.clone()));
+    let last_exit = match crate::get_last_sidecar_exit(app_handle.clone()) {
+        Ok(report) => to_value(&report),
+        Err(error) => serde_json::json!({ "error": error }),
+    };
+    let data_dir = match crate::sidecar_data_dir(&app_handle) {
+        Ok(dir) => serde_json::json!({
+            "path": dir.to_string_lossy(),
+            "size_bytes": dir_size(&dir),
+        }),
+        Err(error) => serde_json::json!({ "error": error }),
+    };
+    let mut settings = Value::Object(crate::settings::entries(&app_handle).into_iter().collect());
+    redact_settings(&mut settings, &redactor);
+
+    write_zip(
+        &path,
+        &[
+            ("sidecar.log", log),
+            ("sidecar-status.json", to_pretty(&status)),
+            ("app-info.json", to_pretty(&app_info)),
+            ("last-exit.json", to_pretty(&last_exit)),
+            ("data-dir.json", to_pretty(&data_dir)),
+            ("settings.json", to_pretty(&settings)),
+        ],
+    )?;
+    tracing::info!("Diagnostics written to {}", path.display());
+    Ok(Some(path))
+}
+
+fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn to_pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+// The end of sidecar.log, or the in-memory buffer when there is no log file
+fn log_tail(app_handle: &tauri::AppHandle) -> String {
+    let from_file = crate::sidecar_log_path(app_handle)
+        .ok()
+        .and_then(|path| read_tail(&path, LOG_TAIL_BYTES).ok());
+    if let Some(tail) = from_file {
+        return tail;
+    }
+    app_handle
+        .try_state::<crate::sidecar_log::LogBuffer>()
+        .map(|buffer| {
+            buffer
+                .snapshot()
+                .iter()
+                .map(|entry| format!("[{}] {}\n", entry.stream, entry.line.trim_end()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Total size of the files below `dir`; unreadable entries count as empty
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok(String::from_utf8_lossy(&tail).into_owned())
+}
+
+fn redact_settings(value: &mut Value, redactor: &Redactor) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if SECRET_KEY_HINTS.iter().any(|hint| key.contains(hint)) && !value.is_null() {
+                    *value = Value::String(redact::REDACTED.to_string());
+                } else {
+                    redact_settings(value, redactor);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_settings(item, redactor)),
+        Value::String(text) => *text = redactor.redact(text).into_owned(),
+        _ => {}
+    }
+}
+
+fn write_zip(path: &Path, files: &[(&str, String)]) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    for (name, contents) in files {
+        zip.start_file(*name, SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        zip.write_all(contents.as_bytes())
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tauri::Emitter;
+
+const DEFAULT_EXTENSIONS: [&str; 2] = ["pdf", "epub"];
+// Directory names never descended into
+const IGNORED_DIRS: [&str; 6] = [
+    ".git",
+    "node_modules",
+    "__pycache__",
+    ".venv",
+    ".Trash",
+    "$RECYCLE.BIN",
+];
+// A scan-progress event is emitted every this many files
+const PROGRESS_EVERY: usize = 500;
+
+#[derive(Serialize)]
+pub struct DocumentCandidate {
+    pub path: String,
+    pub size_bytes: u64,
+    // Unix seconds, None where the platform doesn't report it
+    pub modified: Option<u64>,
+    pub extension: String,
+}
+
+// An entry that couldn't be read; the scan carries on without it
+#[derive(Serialize)]
+pub struct ScanError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ScanResult {
+    pub documents: Vec<DocumentCandidate>,
+    pub errors: Vec<ScanError>,
+    // Set when cancel_scan stopped the scan early; documents holds what was found
+    pub cancelled: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ScanProgress {
+    scan_id: String,
+    files_seen: usize,
+    documents_found: usize,
+}
+
+// Cancellation flags of the running scans, keyed by the caller-chosen scan id
+#[derive(Default)]
+pub struct ScanRegistry(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl ScanRegistry {
+    fn register(&self, scan_id: &str) -> Result<Arc<AtomicBool>, String> {
+        let mut scans = self.0.lock().unwrap();
+        if scans.contains_key(scan_id) {
+            return Err(format!("A scan with id '{}' is already running", scan_id));
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        scans.insert(scan_id.to_string(), flag.clone());
+        Ok(flag)
+    }
+
+    fn unregister(&self, scan_id: &str) {
+        self.0.lock().unwrap().remove(scan_id);
+    }
+
+    // Returns false if no scan with this id is running
+    pub fn cancel(&self, scan_id: &str) -> bool {
+        match self.0.lock().unwrap().get(scan_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct ScanOptions {
+    pub root: PathBuf,
+    // Lowercase, without the leading dot
+    pub extensions: HashSet<String>,
+    // None scans without a depth limit; 0 only looks at the root's own files
+    pub max_depth: Option<usize>,
+}
+
+impl ScanOptions {
+    pub fn new(root: String, extensions: Option<Vec<String>>, max_depth: Option<usize>) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            extensions: document_extensions(extensions),
+            max_depth,
+        }
+    }
+}
+
+// Lowercase the requested extensions and strip leading dots, defaulting to
+// PDF and EPUB when none are given
+pub fn document_extensions(extensions: Option<Vec<String>>) -> HashSet<String> {
+    match extensions {
+        Some(extensions) if !extensions.is_empty() => extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect(),
+        _ => DEFAULT_EXTENSIONS
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect(),
+    }
+}
+
+// Walk the folder on the calling thread; run it through spawn_blocking
+pub fn scan(
+    app_handle: &tauri::AppHandle,
+    registry: &ScanRegistry,
+    scan_id: &str,
+    options: ScanOptions,
+) -> Result<ScanResult, String> {
+    if !options.root.is_dir() {
+        return Err(format!("{} is not a directory", options.root.display()));
+    }
+    let cancelled = registry.register(scan_id)?;
+    let result = Walker {
+        app_handle,
+        scan_id,
+        options: &options,
+        cancelled: &cancelled,
+        visited: HashSet::new(),
+        files_seen: 0,
+        result: ScanResult {
+            documents: Vec::new(),
+            errors: Vec::new(),
+            cancelled: false,
+        },
+    }
+    .run();
+    registry.unregister(scan_id);
+    Ok(result)
+}
+
+struct Walker<'a> {
+    app_handle: &'a tauri::AppHandle,
+    scan_id: &'a str,
+    options: &'a ScanOptions,
+    cancelled: &'a AtomicBool,
+    // Canonical paths of the directories already walked, so symlink loops end
+    visited: HashSet<PathBuf>,
+    files_seen: usize,
+    result: ScanResult,
+}
+
+impl Walker<'_> {
+    fn run(mut self) -> ScanResult {
+        let mut pending = vec![(self.options.root.clone(), 0)];
+        while let Some((dir, depth)) = pending.pop() {
+            if self.cancelled.load(Ordering::Relaxed) {
+                self.result.cancelled = true;
+                break;
+            }
+            match fs::canonicalize(&dir) {
+                Ok(canonical) if !self.visited.insert(canonical) => continue,
+                Ok(_) => {}
+                Err(e) => {
+                    self.error(&dir, e);
+                    continue;
+                }
+            }
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.error(&dir, e);
+                    continue;
+                }
+            };
+            for entry in entries {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => {
+                        self.error(&dir, e);
+                        continue;
+                    }
+                };
+                // Follows symlinks; loops are caught by the visited set above
+                let metadata = match fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        self.error(&path, e);
+                        continue;
+                    }
+                };
+                if metadata.is_dir() {
+                    let ignored = path.file_name().is_some_and(|name| {
+                        IGNORED_DIRS
+                            .iter()
+                            .any(|ignored| name == OsStr::new(ignored))
+                    });
+                    if !ignored && self.options.max_depth.is_none_or(|max| depth < max) {
+                        pending.push((path, depth + 1));
+                    }
+                } else if metadata.is_file() {
+                    self.on_file(path, &metadata);
+                }
+            }
+        }
+        self.result.documents.sort_by(|a, b| a.path.cmp(&b.path));
+        self.result
+    }
+
+    fn on_file(&mut self, path: PathBuf, metadata: &fs::Metadata) {
+        self.files_seen += 1;
+        if self.files_seen.is_multiple_of(PROGRESS_EVERY) {
+            self.app_handle
+                .emit(
+                    "scan-progress",
+                    ScanProgress {
+                        scan_id: self.scan_id.to_string(),
+                        files_seen: self.files_seen,
+                        documents_found: self.result.documents.len(),
+                    },
+                )
+                .ok();
+        }
+        let Some(extension) = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        else {
+            return;
+        };
+        if !self.options.extensions.contains(&extension) {
+            return;
+        }
+        self.result.documents.push(DocumentCandidate {
+            path: path.to_string_lossy().to_string(),
+            size_bytes: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_secs()),
+            extension,
+        });
+    }
+
+    fn error(&mut self, path: &Path, error: std::io::Error) {
+        self.result.errors.push(ScanError {
+            path: path.to_string_lossy().to_string(),
+            message: error.to_string(),
+        });
+    }
+}
@@ -0,0 +1,205 @@
+use crate::error::ChikenError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Manager, Url};
+use tauri_plugin_opener::OpenerExt;
+
+// Folders the user picked in the native dialog of pick_directory, persisted so
+// citations from earlier sessions still open. Only that dialog grants a folder:
+// a path passed in by the webview can't widen the roots, it can only name a
+// folder under one (see DocumentRoots::require).
+const SETTINGS_KEY: &str = "documents.allowed_roots";
+
+// Canonical paths of the granted folders
+#[derive(Default)]
+pub struct DocumentRoots(Mutex<Vec<PathBuf>>);
+
+impl DocumentRoots {
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        let roots = crate::settings::get::<Vec<String>>(app_handle, SETTINGS_KEY)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .collect();
+        Self(Mutex::new(roots))
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|root| path.starts_with(root))
+    }
+
+    // The canonical form of `dir` if it lies inside a granted folder, for
+    // commands like a scan or a folder watch that must not reach beyond them
+    pub fn require(&self, dir: &Path) -> Result<PathBuf, ChikenError> {
+        let dir = fs::canonicalize(dir).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                ChikenError::NotFound(format!("{} does not exist", dir.display()))
+            }
+            _ => ChikenError::Other(format!("Cannot access {}: {}", dir.display(), e)),
+        })?;
+        if !self.contains(&dir) {
+            return Err(ChikenError::AccessDenied(format!(
+                "{} is outside the folders ChiKen was given access to",
+                dir.display()
+            )));
+        }
+        Ok(dir)
+    }
+}
+
+// The granted folder `dir` lies in, resolved; see DocumentRoots::require
+pub fn require(app_handle: &tauri::AppHandle, dir: &Path) -> Result<PathBuf, ChikenError> {
+    app_handle
+        .try_state::<DocumentRoots>()
+        .ok_or("Document folders not found.")?
+        .require(dir)
+}
+
+// Remember `dir`, which the user picked in a native dialog, as granted. Failures
+// only log, picking the folder still succeeded.
+pub fn grant(app_handle: &tauri::AppHandle, dir: &Path) {
+    let Some(roots) = app_handle.try_state::<DocumentRoots>() else {
+        return;
+    };
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return;
+    };
+    let saved: Vec<String> = {
+        let mut roots = roots.0.lock().unwrap();
+        if roots.iter().any(|root| dir.starts_with(root)) {
+            return;
+        }
+        // A new parent folder replaces the roots it contains
+        roots.retain(|root| !root.starts_with(&dir));
+        roots.push(dir);
+        roots
+            .iter()
+            .map(|root| root.to_string_lossy().to_string())
+            .collect()
+    };
+    if let Err(e) = crate::settings::set(app_handle, SETTINGS_KEY, saved) {
+        tracing::warn!("Failed to save document folders: {}", e);
+    }
+}
+
+// Open a cited document with the OS default handler, provided it lies inside a
+// granted folder once symlinks and ".." are resolved.
+//
+// `page` is passed on as a #page=N fragment of a file URL, which browsers and
+// most PDF viewers that take URLs honour. The OS gives no way to ask the
+// default handler what it supports, so if opening the URL fails the file is
+// opened without a page instead.
+pub fn open(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    page: Option<u32>,
+) -> Result<(), ChikenError> {
+    let path = fs::canonicalize(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => ChikenError::NotFound(format!("{} does not exist", path)),
+        _ => ChikenError::Other(format!("Cannot open {}: {}", path, e)),
+    })?;
+    if !path.is_file() {
+        return Err(ChikenError::NotFound(format!(
+            "{} is not a file",
+            path.display()
+        )));
+    }
+    let roots = app_handle
+        .try_state::<DocumentRoots>()
+        .ok_or("Document folders not found.")?;
+    if !roots.contains(&path) {
+        return Err(ChikenError::AccessDenied(format!(
+            "{} is outside the folders ChiKen was given access to",
+            path.display()
+        )));
+    }
+
+    let opener = app_handle.opener();
+    if let Some(page) = page.filter(|page| *page > 0) {
+        if let Ok(mut url) = Url::from_file_path(&path) {
+            url.set_fragment(Some(&format!("page={}", page)));
+            match opener.open_url(url.as_str(), None::<&str>) {
+                Ok(()) => return Ok(()),
+                Err(e) => tracing::warn!(
+                    "Opening {} at page {} failed, opening without a page: {}",
+                    path.display(),
+                    page,
+                    e
+                ),
+            }
+        }
+    }
+    opener
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed again by the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chiken-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn require_accepts_folders_inside_a_granted_root() {
+        let root = temp_dir("roots-inside");
+        fs::create_dir_all(root.join("papers/2024")).unwrap();
+        let roots = DocumentRoots(Mutex::new(vec![root.clone()]));
+        assert_eq!(roots.require(&root).unwrap(), root);
+        assert_eq!(
+            roots.require(&root.join("papers/2024")).unwrap(),
+            root.join("papers/2024")
+        );
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn require_rejects_folders_outside_the_roots() {
+        let parent = temp_dir("roots-outside");
+        fs::create_dir_all(parent.join("granted")).unwrap();
+        fs::create_dir_all(parent.join("other")).unwrap();
+        let roots = DocumentRoots(Mutex::new(vec![parent.join("granted")]));
+        for dir in [
+            parent.clone(),
+            parent.join("other"),
+            // ".." is resolved before the check
+            parent.join("granted/../other"),
+        ] {
+            assert!(
+                matches!(roots.require(&dir), Err(ChikenError::AccessDenied(_))),
+                "{}",
+                dir.display()
+            );
+        }
+        // No roots granted yet
+        let empty = DocumentRoots::default();
+        assert!(matches!(
+            empty.require(&parent.join("granted")),
+            Err(ChikenError::AccessDenied(_))
+        ));
+        fs::remove_dir_all(&parent).ok();
+    }
+
+    #[test]
+    fn require_reports_missing_folders() {
+        let root = temp_dir("roots-missing");
+        let roots = DocumentRoots(Mutex::new(vec![root.clone()]));
+        assert!(matches!(
+            roots.require(&root.join("gone")),
+            Err(ChikenError::NotFound(_))
+        ));
+        fs::remove_dir_all(&root).ok();
+    }
+}
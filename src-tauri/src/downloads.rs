@@ -0,0 +1,350 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tauri_plugin_http::reqwest::{self, header, StatusCode};
+use tokio::sync::Semaphore;
+
+// Downloads beyond this many wait in the queue
+const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+// A download-progress event is emitted at most this often per download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+// Unfinished data lives next to the destination as <dest>.part and is resumed
+// with a range request on the next attempt
+const PART_SUFFIX: &str = ".part";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Verifying,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DownloadInfo {
+    pub id: String,
+    pub url: String,
+    pub dest_path: String,
+    pub state: DownloadState,
+    pub bytes: u64,
+    // None until the server reports a length
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadProgress {
+    id: String,
+    bytes: u64,
+    total: Option<u64>,
+    // Bytes per second since the previous progress event
+    speed: u64,
+}
+
+struct Download {
+    info: DownloadInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+// Downloads started this run, finished ones included so list_downloads can
+// report how they ended
+pub struct Downloads {
+    downloads: Mutex<HashMap<String, Download>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Default for Downloads {
+    fn default() -> Self {
+        Self {
+            downloads: Mutex::new(HashMap::new()),
+            slots: Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS)),
+        }
+    }
+}
+
+impl Downloads {
+    pub fn list(&self) -> Vec<DownloadInfo> {
+        let mut downloads: Vec<DownloadInfo> = self
+            .downloads
+            .lock()
+            .unwrap()
+            .values()
+            .map(|download| download.info.clone())
+            .collect();
+        downloads.sort_by(|a, b| a.dest_path.cmp(&b.dest_path));
+        downloads
+    }
+
+    // Returns false if no unfinished download has this id. The .part file is
+    // kept so a later start_download of the same file resumes it.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.downloads.lock().unwrap().get(id) {
+            Some(download) if is_active(download.info.state) => {
+                download.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&self, id: &str, update: impl FnOnce(&mut DownloadInfo)) {
+        if let Some(download) = self.downloads.lock().unwrap().get_mut(id) {
+            update(&mut download.info);
+        }
+    }
+}
+
+fn is_active(state: DownloadState) -> bool {
+    matches!(
+        state,
+        DownloadState::Queued | DownloadState::Downloading | DownloadState::Verifying
+    )
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut part = dest.as_os_str().to_owned();
+    part.push(PART_SUFFIX);
+    PathBuf::from(part)
+}
+
+// Queue a download of `url` to `dest_path` and return its id. Progress arrives
+// as download-progress events and the outcome as download-finished.
+pub fn start(
+    app_handle: &tauri::AppHandle,
+    url: String,
+    dest_path: String,
+    sha256: Option<String>,
+) -> Result<String, String> {
+    let downloads = app_handle
+        .try_state::<Downloads>()
+        .ok_or("Download manager not found.")?;
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Download URL must be http(s): {}", url));
+    }
+    let dest = PathBuf::from(&dest_path);
+    if !dest.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(format!("The folder for {} does not exist", dest.display()));
+    }
+    let sha256 = sha256.map(|sha256| sha256.trim().to_ascii_lowercase());
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut active = downloads.downloads.lock().unwrap();
+        if active
+            .values()
+            .any(|download| is_active(download.info.state) && download.info.dest_path == dest_path)
+        {
+            return Err(format!("{} is already being downloaded", dest_path));
+        }
+        active.insert(
+            id.clone(),
+            Download {
+                info: DownloadInfo {
+                    id: id.clone(),
+                    url: url.clone(),
+                    dest_path,
+                    state: DownloadState::Queued,
+                    bytes: 0,
+                    total: None,
+                    error: None,
+                },
+                cancelled: cancelled.clone(),
+            },
+        );
+    }
+
+    let app_handle = app_handle.clone();
+    let slots = downloads.slots.clone();
+    let task_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        // Held until the download ends, capping how many run at once
+        let Ok(_slot) = slots.acquire_owned().await else {
+            return;
+        };
+        let job = Job {
+            app_handle: &app_handle,
+            id: &task_id,
+            url: &url,
+            dest: &dest,
+            sha256: sha256.as_deref(),
+            cancelled: &cancelled,
+        };
+        let (state, error) = match job.run().await {
+            Ok(()) => (DownloadState::Completed, None),
+            Err(_) if cancelled.load(Ordering::Relaxed) => (DownloadState::Cancelled, None),
+            Err(e) => {
+                tracing::warn!("Download of {} failed: {}", url, e);
+                (DownloadState::Failed, Some(e))
+            }
+        };
+        job.set_state(state, error);
+        let info = app_handle
+            .state::<Downloads>()
+            .downloads
+            .lock()
+            .unwrap()
+            .get(&task_id)
+            .map(|download| download.info.clone());
+        if let Some(info) = info {
+            app_handle.emit("download-finished", info).ok();
+        }
+    });
+    Ok(id)
+}
+
+struct Job<'a> {
+    app_handle: &'a tauri::AppHandle,
+    id: &'a str,
+    url: &'a str,
+    dest: &'a Path,
+    // Expected lowercase hex digest, checked before the file is moved into place
+    sha256: Option<&'a str>,
+    cancelled: &'a AtomicBool,
+}
+
+impl Job<'_> {
+    async fn run(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+        self.set_state(DownloadState::Downloading, None);
+        let part = part_path(self.dest);
+        let mut offset = fs::metadata(&part)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+
+        let mut request = reqwest::Client::new().get(self.url);
+        if offset > 0 {
+            request = request.header(header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status();
+        let complete = if status == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+            // The .part file already holds everything the server has
+            true
+        } else if !status.is_success() {
+            return Err(format!("Server responded with {}", status));
+        } else {
+            if status != StatusCode::PARTIAL_CONTENT && offset > 0 {
+                tracing::info!(
+                    "Server ignored the range request for {}, restarting",
+                    self.url
+                );
+                offset = 0;
+            }
+            false
+        };
+
+        if !complete {
+            let total = response
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(|range| range.rsplit('/').next())
+                .and_then(|total| total.parse().ok())
+                .or_else(|| response.content_length().map(|length| length + offset));
+            self.app_handle
+                .state::<Downloads>()
+                .update(self.id, |info| {
+                    info.bytes = offset;
+                    info.total = total;
+                });
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(offset > 0)
+                .truncate(offset == 0)
+                .open(&part)
+                .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+            let mut bytes = offset;
+            let mut last_report = (Instant::now(), bytes);
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| format!("Download interrupted: {}", e))?
+            {
+                if self.cancelled.load(Ordering::Relaxed) {
+                    return Err("Cancelled".to_string());
+                }
+                file.write_all(&chunk)
+                    .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+                bytes += chunk.len() as u64;
+                let elapsed = last_report.0.elapsed();
+                if elapsed >= PROGRESS_INTERVAL {
+                    let speed = ((bytes - last_report.1) as f64 / elapsed.as_secs_f64()) as u64;
+                    self.progress(bytes, total, speed);
+                    last_report = (Instant::now(), bytes);
+                }
+            }
+            file.flush()
+                .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+            self.progress(bytes, total, 0);
+            if total.is_some_and(|total| bytes < total) {
+                return Err(format!("Connection closed after {} bytes", bytes));
+            }
+        }
+
+        if let Some(expected) = self.sha256 {
+            self.set_state(DownloadState::Verifying, None);
+            let path = part.to_string_lossy().to_string();
+            let (actual, _) =
+                tauri::async_runtime::spawn_blocking(move || crate::file_hash::sha256_file(&path))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| format!("Failed to hash {}: {}", part.display(), e))?;
+            if actual != expected {
+                // A corrupt .part would otherwise be resumed forever
+                fs::remove_file(&part).ok();
+                return Err(format!(
+                    "Checksum mismatch: expected {}, got {}",
+                    expected, actual
+                ));
+            }
+        }
+        fs::rename(&part, self.dest)
+            .map_err(|e| format!("Failed to move download to {}: {}", self.dest.display(), e))?;
+        tracing::info!("Downloaded {} to {}", self.url, self.dest.display());
+        Ok(())
+    }
+
+    fn progress(&self, bytes: u64, total: Option<u64>, speed: u64) {
+        self.app_handle
+            .state::<Downloads>()
+            .update(self.id, |info| info.bytes = bytes);
+        self.app_handle
+            .emit(
+                "download-progress",
+                DownloadProgress {
+                    id: self.id.to_string(),
+                    bytes,
+                    total,
+                    speed,
+                },
+            )
+            .ok();
+    }
+
+    fn set_state(&self, state: DownloadState, error: Option<String>) {
+        self.app_handle
+            .state::<Downloads>()
+            .update(self.id, |info| {
+                info.state = state;
+                info.error = error;
+            });
+    }
+}
@@ -0,0 +1,77 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+// Error returned by every command. It reaches the frontend as
+// {"code": "sidecar_not_running", "message": "..."}: `code` is stable and meant
+// for branching in the UI, `message` is for display and may change.
+#[derive(Debug, thiserror::Error)]
+pub enum ChikenError {
+    #[error("The backend is not running.")]
+    SidecarNotRunning,
+    #[error("Failed to start the backend: {message}")]
+    SidecarSpawnFailed { message: String },
+    #[error("External backend mode: using {url}, the bundled sidecar is not managed by the app.")]
+    ExternalBackend { url: String },
+    // Neither the OS keyring nor the encrypted fallback file could be used
+    #[error("No OS keyring is available and the secrets file can't be used: {0}")]
+    KeyringUnavailable(String),
+    // The keyring is there but rejected the operation
+    #[error("{0}")]
+    Keyring(String),
+    // The user denied the keychain prompt; they can allow it in Keychain Access
+    #[error("{0}")]
+    KeyringAccessDenied(String),
+    // An API key that doesn't match its provider's key format
+    #[error("{0}")]
+    InvalidApiKey(String),
+    #[error("{0}")]
+    NotFound(String),
+    // The path exists but isn't inside a folder the user granted
+    #[error("{0}")]
+    AccessDenied(String),
+    // A remote service (e.g. an AI provider) couldn't be reached
+    #[error("{0}")]
+    Network(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl ChikenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChikenError::SidecarNotRunning => "sidecar_not_running",
+            ChikenError::SidecarSpawnFailed { .. } => "sidecar_spawn_failed",
+            ChikenError::ExternalBackend { .. } => "external_backend",
+            ChikenError::KeyringUnavailable(_) => "keyring_unavailable",
+            ChikenError::Keyring(_) => "keyring_error",
+            ChikenError::KeyringAccessDenied(_) => "keyring_access_denied",
+            ChikenError::InvalidApiKey(_) => "invalid_api_key",
+            ChikenError::NotFound(_) => "not_found",
+            ChikenError::AccessDenied(_) => "access_denied",
+            ChikenError::Network(_) => "network_error",
+            ChikenError::Other(_) => "other",
+        }
+    }
+}
+
+impl Serialize for ChikenError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("ChikenError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+// Most internal helpers still return Result<_, String>; `?` turns those into
+// Other so commands only have to pick a variant where the UI cares
+impl From<String> for ChikenError {
+    fn from(message: String) -> Self {
+        ChikenError::Other(message)
+    }
+}
+
+impl From<&str> for ChikenError {
+    fn from(message: &str) -> Self {
+        ChikenError::Other(message.to_string())
+    }
+}
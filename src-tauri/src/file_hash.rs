@@ -0,0 +1,87 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+// Result for one path; on failure sha256/size are None and error says why
+#[derive(Serialize)]
+pub struct FileHash {
+    pub path: String,
+    // Lowercase hex
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Hash files in parallel, one worker thread per core, returning results in the
+// order of `paths`. Runs on the calling thread; use spawn_blocking.
+pub fn hash_files(paths: Vec<String>) -> Vec<FileHash> {
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<FileHash>>> = Mutex::new(paths.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+                let hash = hash_file(path);
+                results.lock().unwrap()[index] = Some(hash);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn hash_file(path: &str) -> FileHash {
+    match sha256_file(path) {
+        Ok((sha256, size)) => FileHash {
+            path: path.to_string(),
+            sha256: Some(sha256),
+            size: Some(size),
+            error: None,
+        },
+        Err(e) => FileHash {
+            path: path.to_string(),
+            sha256: None,
+            size: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+pub fn sha256_file(path: &str) -> std::io::Result<(String, u64)> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    let mut size = 0;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok((sha256, size))
+}
@@ -0,0 +1,147 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+// A path is only reported once it has seen no events for this long, so a
+// download that is still being written isn't picked up half-finished
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Payload of the documents-folder-changed event
+#[derive(Clone, Serialize)]
+struct FolderChanged {
+    folder: String,
+    created: Vec<String>,
+    modified: Vec<String>,
+    removed: Vec<String>,
+}
+
+struct PendingChange {
+    // The first event seen for the path was a create
+    created: bool,
+    last_event: Instant,
+}
+
+// Active watches keyed by folder. Everything lives in the Rust process, so
+// watches are unaffected by sidecar restarts. Dropping a watcher stops it and
+// closes the channel, which ends its debounce thread.
+#[derive(Default)]
+pub struct FolderWatches(Mutex<HashMap<PathBuf, RecommendedWatcher>>);
+
+impl FolderWatches {
+    pub fn watch(
+        &self,
+        app_handle: tauri::AppHandle,
+        folder: PathBuf,
+        extensions: HashSet<String>,
+    ) -> Result<(), String> {
+        if !folder.is_dir() {
+            return Err(format!("{} is not a directory", folder.display()));
+        }
+        let mut watches = self.0.lock().unwrap();
+        if watches.contains_key(&folder) {
+            return Ok(());
+        }
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            tx.send(event).ok();
+        })
+        .map_err(|e| format!("Failed to create folder watcher: {}", e))?;
+        watcher
+            .watch(&folder, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+
+        let name = folder.to_string_lossy().to_string();
+        thread::Builder::new()
+            .name("folder-watch".to_string())
+            .spawn(move || debounce(app_handle, name, extensions, rx))
+            .map_err(|e| format!("Failed to start folder watcher: {}", e))?;
+        tracing::info!("Watching {} for new documents", folder.display());
+        watches.insert(folder, watcher);
+        Ok(())
+    }
+
+    // Returns false if the folder wasn't being watched
+    pub fn unwatch(&self, folder: &Path) -> bool {
+        self.0.lock().unwrap().remove(folder).is_some()
+    }
+
+    pub fn unwatch_all(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+fn debounce(
+    app_handle: tauri::AppHandle,
+    folder: String,
+    extensions: HashSet<String>,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+) {
+    let mut pending: HashMap<PathBuf, PendingChange> = HashMap::new();
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                let created = matches!(event.kind, EventKind::Create(_));
+                let relevant =
+                    created || matches!(event.kind, EventKind::Modify(_) | EventKind::Remove(_));
+                for path in event.paths.into_iter().filter(|_| relevant) {
+                    let matches_extension = path.extension().is_some_and(|ext| {
+                        extensions.contains(&ext.to_string_lossy().to_ascii_lowercase())
+                    });
+                    if !matches_extension {
+                        continue;
+                    }
+                    pending
+                        .entry(path)
+                        .and_modify(|change| change.last_event = Instant::now())
+                        .or_insert(PendingChange {
+                            created,
+                            last_event: Instant::now(),
+                        });
+                }
+            }
+            Ok(Err(e)) => tracing::warn!("Folder watch error in {}: {}", folder, e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, change)| change.last_event.elapsed() >= QUIET_PERIOD)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if settled.is_empty() {
+            continue;
+        }
+        let mut changed = FolderChanged {
+            folder: folder.clone(),
+            created: Vec::new(),
+            modified: Vec::new(),
+            removed: Vec::new(),
+        };
+        for path in settled {
+            let change = pending.remove(&path).unwrap();
+            let exists = path.exists();
+            let path = path.to_string_lossy().to_string();
+            match (exists, change.created) {
+                (true, true) => changed.created.push(path),
+                (true, false) => changed.modified.push(path),
+                (false, false) => changed.removed.push(path),
+                // Created and deleted again within the quiet period, e.g. a temp file
+                (false, true) => {}
+            }
+        }
+        if !(changed.created.is_empty()
+            && changed.modified.is_empty()
+            && changed.removed.is_empty())
+        {
+            app_handle.emit("documents-folder-changed", changed).ok();
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::env;
+use std::sync::OnceLock;
+
+// Address the sidecar listens on in headless mode unless --host says otherwise:
+// every interface, since the point is reaching it from another machine. The
+// readiness and watchdog probes go to localhost, which this includes.
+const DEFAULT_HOST: &str = "0.0.0.0";
+
+// Parsed once from the command line
+struct Options {
+    enabled: bool,
+    host: Option<String>,
+    port: Option<u16>,
+    token: Option<String>,
+}
+
+static OPTIONS: OnceLock<Options> = OnceLock::new();
+
+fn options() -> &'static Options {
+    OPTIONS.get_or_init(|| {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let value = |flag: &str| {
+            let prefix = format!("{}=", flag);
+            args.iter().enumerate().find_map(|(i, arg)| {
+                if arg == flag {
+                    args.get(i + 1).cloned()
+                } else {
+                    arg.strip_prefix(&prefix).map(str::to_string)
+                }
+            })
+        };
+        Options {
+            enabled: args.iter().any(|arg| arg == "--headless"),
+            host: value("--host"),
+            token: value("--token")
+                .or_else(|| env::var("CHIKEN_AUTH_TOKEN").ok())
+                .filter(|token| !token.trim().is_empty()),
+            port: value("--port").and_then(|port| match port.parse() {
+                Ok(port) => Some(port),
+                Err(_) => {
+                    eprintln!("Ignoring invalid --port {}", port);
+                    None
+                }
+            }),
+        }
+    })
+}
+
+// `--headless`: no windows, tray or global shortcut, just the supervised sidecar,
+// for running the backend on a server and connecting to it from the desktop app
+// in external-backend mode. Commands that need a webview are unavailable.
+pub fn enabled() -> bool {
+    options().enabled
+}
+
+// `--host <addr>` for the sidecar in headless mode; None outside of it, where
+// the backend keeps its own default
+pub fn host() -> Option<&'static str> {
+    let options = options();
+    options
+        .enabled
+        .then(|| options.host.as_deref().unwrap_or(DEFAULT_HOST))
+}
+
+// `--port <n>` in headless mode, used instead of picking a free port so clients
+// have a fixed address to connect to
+pub fn port() -> Option<u16> {
+    let options = options();
+    options.port.filter(|_| options.enabled)
+}
+
+// `--token <secret>`, or CHIKEN_AUTH_TOKEN from the environment, in headless
+// mode: the token clients must send, so a desktop app pointed at this instance
+// can be configured with it once. None outside of headless mode and when
+// neither is given, in which case a random token is generated and printed.
+pub fn token() -> Option<&'static str> {
+    let options = options();
+    options.token.as_deref().filter(|_| options.enabled)
+}
+
+// Drop the windows from the config so none is created at startup
+pub fn apply<R: tauri::Runtime>(context: &mut tauri::Context<R>) {
+    if enabled() {
+        context.config_mut().app.windows.clear();
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{
+    Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutEvent, ShortcutState,
+};
+
+// System-wide shortcut that toggles the main window like a launcher. The
+// accelerator is persisted as window.global_shortcut; an empty string means the
+// user turned it off, a missing key means DEFAULT_SHORTCUT.
+const SETTINGS_KEY: &str = "window.global_shortcut";
+const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+// The shortcut currently registered with the OS
+#[derive(Default)]
+pub struct GlobalShortcut(Mutex<Option<Shortcut>>);
+
+fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse::<Shortcut>()
+        .map_err(|e| format!("Invalid shortcut {}: {}", accelerator, e))
+}
+
+// F11, or Ctrl+Cmd+F on macOS, toggles fullscreen. It is registered only while
+// the main window has focus, so it works before the webview has loaded without
+// taking the key away from other apps.
+fn fullscreen_shortcut() -> Shortcut {
+    if cfg!(target_os = "macos") {
+        Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SUPER), Code::KeyF)
+    } else {
+        Shortcut::new(None, Code::F11)
+    }
+}
+
+// Handler passed to the plugin builder, for the fullscreen shortcut and the
+// user's show/hide one
+pub fn on_shortcut(app_handle: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+    if *shortcut == fullscreen_shortcut() {
+        toggle_fullscreen(app_handle);
+    } else {
+        toggle_main_window(app_handle);
+    }
+}
+
+fn toggle_fullscreen(app_handle: &AppHandle) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    if let Err(e) = crate::toggle_window_fullscreen(&window.as_ref().window(), false) {
+        tracing::warn!("{}", e);
+    }
+}
+
+// Called as the main window gains or loses focus
+pub fn set_fullscreen_shortcut(app_handle: &AppHandle, active: bool) {
+    let global_shortcut = app_handle.global_shortcut();
+    let shortcut = fullscreen_shortcut();
+    let result = if active {
+        global_shortcut.register(shortcut)
+    } else {
+        global_shortcut.unregister(shortcut)
+    };
+    if let Err(e) = result {
+        tracing::debug!(
+            "Fullscreen shortcut not {}: {}",
+            if active { "registered" } else { "unregistered" },
+            e
+        );
+    }
+}
+
+// Hide the main window when it has focus, otherwise bring it to the front,
+// restoring it from minimized or from the tray
+fn toggle_main_window(app_handle: &AppHandle) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) {
+        window.hide().ok();
+        return;
+    }
+    // Activates the app, so the window comes forward on the current space instead
+    // of switching to the one it was last shown on
+    #[cfg(target_os = "macos")]
+    app_handle.show().ok();
+    crate::focus_main_window(app_handle);
+}
+
+// Register the saved (or default) shortcut during setup. Failing here, e.g.
+// because another app owns the accelerator, only logs; the settings page can
+// pick a different one.
+pub fn restore(app_handle: &AppHandle) {
+    let accelerator = crate::settings::get::<String>(app_handle, SETTINGS_KEY)
+        .unwrap_or_else(|| DEFAULT_SHORTCUT.to_string());
+    if accelerator.is_empty() {
+        return;
+    }
+    if let Err(e) = replace(app_handle, Some(&accelerator)) {
+        tracing::warn!("Global shortcut not registered: {}", e);
+    }
+}
+
+// Swap the registered shortcut for `accelerator` (None unregisters it). If the
+// new one can't be registered the previous one is put back.
+fn replace(app_handle: &AppHandle, accelerator: Option<&str>) -> Result<(), String> {
+    let state = app_handle
+        .try_state::<GlobalShortcut>()
+        .ok_or("Global shortcut state not found.")?;
+    let shortcut = accelerator.map(parse).transpose()?;
+    let mut current = state.0.lock().unwrap();
+    if *current == shortcut {
+        return Ok(());
+    }
+    let global_shortcut = app_handle.global_shortcut();
+    let previous = current.take();
+    if let Some(previous) = previous {
+        global_shortcut.unregister(previous).ok();
+    }
+    if let Some(shortcut) = shortcut {
+        if let Err(e) = global_shortcut.register(shortcut) {
+            if let Some(previous) = previous {
+                if global_shortcut.register(previous).is_ok() {
+                    *current = Some(previous);
+                }
+            }
+            return Err(format!(
+                "{} could not be registered, it may already be used by another application ({})",
+                accelerator.unwrap_or_default(),
+                e
+            ));
+        }
+        tracing::info!(
+            "Registered global shortcut {}",
+            accelerator.unwrap_or_default()
+        );
+        *current = Some(shortcut);
+    }
+    Ok(())
+}
+
+pub fn set(app_handle: &AppHandle, accelerator: &str) -> Result<(), String> {
+    let accelerator = accelerator.trim();
+    replace(app_handle, Some(accelerator))?;
+    crate::settings::set(app_handle, SETTINGS_KEY, accelerator)
+}
+
+pub fn clear(app_handle: &AppHandle) -> Result<(), String> {
+    replace(app_handle, None)?;
+    crate::settings::set(app_handle, SETTINGS_KEY, "")
+}
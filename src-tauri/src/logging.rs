@@ -0,0 +1,228 @@
+use crate::sidecar_log::LogLevel;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use tauri::Manager;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+// Filter directives in EnvFilter syntax, e.g. CHIKEN_LOG=debug or
+// CHIKEN_LOG=info,app::downloads=trace. Overrides logging.level at startup.
+const FILTER_ENV: &str = "CHIKEN_LOG";
+// Verbosity set through set_log_level, one of LEVELS
+const LEVEL_SETTING: &str = "logging.level";
+const LEVELS: [&str; 4] = ["error", "warn", "info", "debug"];
+const DEFAULT_LEVEL: &str = "info";
+// Daily files named chiken.<date>.log next to sidecar.log, a week of them kept
+const FILE_PREFIX: &str = "chiken";
+const MAX_LOG_FILES: usize = 7;
+
+fn log_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))
+}
+
+fn file_appender(log_dir: &Path) -> Result<RollingFileAppender, String> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|e| format!("Failed to open log file in {}: {}", log_dir.display(), e))
+}
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+// Least severe sidecar line that is still logged and forwarded, a LogLevel as u8
+static SIDECAR_THRESHOLD: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+fn sidecar_threshold(level: &str) -> LogLevel {
+    match level {
+        "error" => LogLevel::Error,
+        "warn" => LogLevel::Warning,
+        "debug" => LogLevel::Debug,
+        _ => LogLevel::Info,
+    }
+}
+
+// The stored verbosity, "info" unless set_log_level picked another
+pub fn level(app_handle: &tauri::AppHandle) -> String {
+    crate::settings::get::<String>(app_handle, LEVEL_SETTING)
+        .filter(|level| LEVELS.contains(&level.as_str()))
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string())
+}
+
+// Switch the app's log filter and which sidecar lines are kept, and remember the
+// choice. The sidecar gets the level as CHIKEN_LOG_LEVEL when it is next spawned.
+pub fn set_level(app_handle: &tauri::AppHandle, level: &str) -> Result<(), String> {
+    let level = level.to_ascii_lowercase();
+    if !LEVELS.contains(&level.as_str()) {
+        return Err(format!(
+            "Unknown log level '{}', expected one of {}",
+            level,
+            LEVELS.join(", ")
+        ));
+    }
+    if let Some(filter) = FILTER.get() {
+        filter
+            .reload(EnvFilter::new(&level))
+            .map_err(|e| format!("Failed to change log level: {}", e))?;
+    }
+    SIDECAR_THRESHOLD.store(sidecar_threshold(&level) as u8, Ordering::Relaxed);
+    crate::settings::set(app_handle, LEVEL_SETTING, level.as_str())?;
+    tracing::info!("Log level set to {}", level);
+    Ok(())
+}
+
+// Whether a sidecar line at `level` passes the current verbosity
+pub fn sidecar_enabled(level: LogLevel) -> bool {
+    level as u8 >= SIDECAR_THRESHOLD.load(Ordering::Relaxed)
+}
+
+// Install the global subscriber: stdout in debug builds and in --headless mode,
+// otherwise a rolling file under app_log_dir, since release builds on Windows
+// have no console. Falls back to stdout when the log file can't be opened and
+// returns why.
+// Runs first thing in setup; anything logged before that is dropped.
+pub fn init(app_handle: &tauri::AppHandle) -> Option<String> {
+    let level = level(app_handle);
+    SIDECAR_THRESHOLD.store(sidecar_threshold(&level) as u8, Ordering::Relaxed);
+    let filter = EnvFilter::try_from_env(FILTER_ENV).unwrap_or_else(|_| EnvFilter::new(&level));
+    let (filter, handle) = reload::Layer::new(filter);
+    FILTER.set(handle).ok();
+    let (file, file_error) = if cfg!(debug_assertions) || crate::headless::enabled() {
+        (None, None)
+    } else {
+        match log_dir(app_handle).and_then(|dir| file_appender(&dir)) {
+            Ok(appender) => (Some(appender), None),
+            Err(e) => (None, Some(e)),
+        }
+    };
+    subscriber(filter, file, std::io::stdout).init();
+    if let Some(e) = &file_error {
+        tracing::warn!("Logging to stdout instead of a file: {}", e);
+    }
+    file_error
+}
+
+// Writes to `file` if there is one, to `console` (stdout outside tests) otherwise
+fn subscriber<W>(
+    filter: reload::Layer<EnvFilter, Registry>,
+    file: Option<RollingFileAppender>,
+    console: W,
+) -> impl tracing::Subscriber + Send + Sync + 'static
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let console = file.is_none().then(|| fmt::layer().with_writer(console));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console)
+        .with(file.map(|appender| fmt::layer().with_ansi(false).with_writer(appender)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    // Stands in for stdout so tests can see what reached the console
+    #[derive(Clone, Default)]
+    struct Console(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Console {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // A fresh directory under the system temp dir, removed again by the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chiken-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    fn set_mode(dir: &Path, mode: u32) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    // Logging carries on to stdout, whatever kept the file from opening
+    fn assert_console_fallback(file: Result<RollingFileAppender, String>) {
+        let (filter, _handle) = reload::Layer::new(EnvFilter::new("info"));
+        let console = Console::default();
+        let writer = console.clone();
+        let subscriber = subscriber(filter, file.ok(), move || writer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("Logging to stdout instead of a file");
+        });
+        let output = String::from_utf8(console.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            output.contains("Logging to stdout instead of a file"),
+            "{:?}",
+            output
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_only_log_dir_falls_back_to_console() {
+        let parent = temp_dir("logging-readonly");
+        set_mode(&parent, 0o555);
+        let file = file_appender(&parent.join("logs"));
+        // Permissions don't hold back root, so only check the error where they apply
+        let enforced = fs::create_dir(parent.join("probe")).is_err();
+        set_mode(&parent, 0o755);
+        if enforced {
+            match &file {
+                Ok(_) => panic!("log dir should not be writable"),
+                Err(e) => assert!(e.starts_with("Failed to open log file in"), "{}", e),
+            }
+        }
+        assert_console_fallback(file);
+        fs::remove_dir_all(&parent).ok();
+    }
+
+    #[test]
+    fn log_dir_that_is_a_file_falls_back_to_console() {
+        let parent = temp_dir("logging-file");
+        let not_a_dir = parent.join("logs");
+        fs::write(&not_a_dir, "").unwrap();
+        let file = file_appender(&not_a_dir.join("chiken"));
+        assert!(file.is_err());
+        assert_console_fallback(file);
+        fs::remove_dir_all(&parent).ok();
+    }
+
+    #[test]
+    fn writable_log_dir_gets_a_file() {
+        let dir = temp_dir("logging-writable");
+        let file = file_appender(&dir.join("logs"));
+        assert!(file.is_ok());
+        // With a file to write to, nothing goes to the console
+        let (filter, _handle) = reload::Layer::new(EnvFilter::new("info"));
+        let console = Console::default();
+        let writer = console.clone();
+        let subscriber = subscriber(filter, file.ok(), move || writer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("Written to the log file");
+        });
+        assert!(console.0.lock().unwrap().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
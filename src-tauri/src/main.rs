@@ -1,33 +1,597 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::path::PathBuf;
+use error::ChikenError;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use std::{
     env,
     sync::{Arc, Mutex},
 };
-use tauri::{Emitter, Manager, RunEvent};
+use tauri::async_runtime::Receiver;
+use tauri::{Emitter, Listener, Manager, RunEvent, WindowEvent};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use tauri_plugin_decorum::WebviewWindowExt;
-use tauri_plugin_dialog;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_dialog::{self, DialogExt, MessageDialogButtons, MessageDialogKind};
 use tauri_plugin_fs;
-use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent, TerminatedPayload};
 use tauri_plugin_shell::ShellExt;
-use tauri_plugin_window_state::{AppHandleExt, StateFlags};
+use tauri_plugin_window_state::{AppHandleExt, StateFlags, WindowExt};
+use tracing::Instrument;
+mod api_keys;
+mod backend_env;
+mod data_archive;
+mod deep_link;
+mod diagnostics;
+mod doc_scan;
+mod document_roots;
+mod downloads;
+mod error;
+mod file_hash;
+mod folder_watch;
+mod headless;
+mod hotkey;
+mod logging;
+mod notifications;
+mod ollama;
+mod process_tree;
+mod profile;
+mod redact;
+mod secret_file;
 mod secret_store;
+mod settings;
+mod sidecar_log;
+mod signals;
+mod splash;
+mod tray;
+mod watchdog;
+mod zotero;
+
+// How long restart_sidecar waits for the old process to exit before giving up
+const SIDECAR_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+// How long a graceful shutdown waits before killing the sidecar, overridable
+// through the backend.shutdown_timeout_secs setting
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+// Crash auto-restart: exponential backoff starting at 1s and capped at 30s, giving up
+// with a sidecar-failed event once MAX_SIDECAR_RESTARTS restarts have been made
+// within SIDECAR_RESTART_WINDOW. A crash after the window has passed starts a new one.
+const SIDECAR_RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+const SIDECAR_RESTART_WINDOW: Duration = Duration::from_secs(60);
+const MAX_SIDECAR_RESTARTS: u32 = 5;
+// How long a single GET /health probe may take
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+// Readiness probing after spawn. The timeout can be raised through the
+// CHIKEN_STARTUP_TIMEOUT_SECS env var or the backend.startup_timeout_secs setting
+// for slow disks where unpacking the pyinstaller bundle takes a while.
+const READINESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+// Webview zoom factor bounds for set_zoom; the factor is persisted as window.zoom
+// since the window-state plugin doesn't remember it
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+// Window geometry is saved this long after the last move or resize, so it
+// survives a crash and not only a clean exit
+const WINDOW_STATE_SAVE_DELAY: Duration = Duration::from_secs(1);
+// Stdout lines are forwarded to the frontend as sidecar-stdout-batch events, at most
+// every STDOUT_BATCH_INTERVAL or once STDOUT_BATCH_MAX_LINES have accumulated
+const STDOUT_BATCH_INTERVAL: Duration = Duration::from_millis(100);
+const STDOUT_BATCH_MAX_LINES: usize = 200;
+// Stdout lines with these prefixes are messages from the backend rather than log
+// output: a {"title", "body", "kind"} notification request, and a {"stage", "pct"}
+// startup progress update re-emitted as sidecar-progress
+const NOTIFY_PREFIX: &str = "@@notify@@";
+const PROGRESS_PREFIX: &str = "@@progress@@";
+// `@@JOB_DONE {"job", "title", "body", "kind"}` marks a finished long-running job
+// and is turned into a notification
+const JOB_DONE_PREFIX: &str = "@@JOB_DONE ";
+// `@@badge@@3` sets the dock badge to the number of running jobs; `@@badge@@0`
+// or an empty count clears it
+const BADGE_PREFIX: &str = "@@badge@@";
+// Printed once by the backend when its HTTP server accepts connections; health
+// polling remains the fallback for backends that never print it
+const READY_LINE: &str = "@@ready@@";
+// Header carrying the per-run auth token the backend requires on every request,
+// so other local processes can't use the API just by finding the port
+const AUTH_TOKEN_HEADER: &str = "X-Chiken-Token";
+// Port the sidecar listens on unless it is taken, and the frontend's default
+const DEFAULT_SIDECAR_PORT: u16 = 8009;
+// Number of trailing stderr lines attached to the sidecar-terminated event
+const STDERR_TAIL_LINES: usize = 50;
+
+// Sidecar process handle plus the bookkeeping needed to know when it has exited
+#[derive(Default)]
+struct SidecarState {
+    // The child handle must stay alive for the stdin pipe to remain connected
+    child: Option<CommandChild>,
+    // Bumped on every spawn so a monitor task only clears the state of its own process
+    generation: u64,
+    // True from spawn until the monitor task observes the process terminating
+    running: bool,
+    // Reserved under the lock while a spawn is in flight so concurrent callers
+    // (setup plus a frontend start_sidecar) can't both start a process
+    spawning: bool,
+    // Guards restart_sidecar against overlapping invocations
+    restarting: bool,
+    // Set while backend.lazy_start holds back the first spawn until the
+    // frontend calls start_sidecar
+    deferred: bool,
+    // Set when the sidecar is being stopped on purpose so its exit is not treated as a crash
+    stopping: bool,
+    // Pid of the current process, kept until it exits even if `child` was taken
+    pid: Option<u32>,
+    // When the current process was spawned
+    started_at: Option<Instant>,
+    // Port the sidecar was told to listen on
+    port: Option<u16>,
+    // Set once the current process answers GET /health
+    ready: bool,
+    // Latest watchdog probe of the current process
+    last_health_check: Option<watchdog::HealthCheck>,
+    // Backend managed outside the app (backend.external_url setting); when set
+    // the bundled sidecar is never spawned
+    external_url: Option<String>,
+    // Automatic restarts made in the current restart window
+    restart_count: u32,
+    // When the first restart of the current window was made
+    restart_window_started: Option<Instant>,
+    // How the most recently terminated process ended
+    last_exit: Option<SidecarExit>,
+    // The same with the details behind get_last_sidecar_exit
+    last_exit_report: Option<SidecarExitReport>,
+}
+
+impl SidecarState {
+    // Check if a sidecar process already exists and reserve the spawn in the same
+    // critical section. False when one is running or another caller is already
+    // starting it; the reservation is released by storing the child or, when
+    // the spawn fails, by clearing `spawning`.
+    fn reserve_spawn(&mut self) -> Result<bool, ChikenError> {
+        if let Some(url) = &self.external_url {
+            return Err(ChikenError::ExternalBackend { url: url.clone() });
+        }
+        if self.child.is_some() || self.spawning {
+            return Ok(false);
+        }
+        self.spawning = true;
+        self.deferred = false;
+        Ok(true)
+    }
+
+    // Count an automatic restart at `now` and return its 1-based attempt number
+    // within the current window, or None once the window's restarts are used up
+    fn next_restart_attempt(&mut self, now: Instant) -> Option<u32> {
+        let in_window = self
+            .restart_window_started
+            .is_some_and(|started| now.duration_since(started) < SIDECAR_RESTART_WINDOW);
+        if !in_window {
+            self.restart_window_started = Some(now);
+            self.restart_count = 0;
+        }
+        if self.restart_count >= MAX_SIDECAR_RESTARTS {
+            return None;
+        }
+        self.restart_count += 1;
+        Some(self.restart_count)
+    }
+
+    fn reset_restarts(&mut self) {
+        self.restart_count = 0;
+        self.restart_window_started = None;
+    }
+}
+
+// Exit information of a terminated sidecar process
+#[derive(Clone, Copy, Serialize)]
+struct SidecarExit {
+    // None when the process was killed by a signal rather than exiting
+    code: Option<i32>,
+    signal: Option<i32>,
+}
+
+// Returned by get_last_sidecar_exit and kept for the rest of the app run, so a
+// crash can still be diagnosed after the sidecar was restarted
+#[derive(Clone, Serialize)]
+struct SidecarExitReport {
+    pid: u32,
+    code: Option<i32>,
+    signal: Option<i32>,
+    // Unix seconds
+    exited_at: u64,
+    // False when the process died on its own rather than via shutdown/restart
+    intentional: bool,
+    // The last lines of stderr up to the exit, oldest first. May reach back into
+    // earlier processes of this run if this one wrote little.
+    stderr_tail: Vec<String>,
+}
+
+type SidecarHandle = Arc<Mutex<SidecarState>>;
+
+static AUTH_TOKEN: OnceLock<String> = OnceLock::new();
+
+// Random token generated once per app run and handed to every sidecar spawned
+// during it as CHIKEN_AUTH_TOKEN; stable across restarts so the frontend only
+// has to fetch it once. In headless mode it can be fixed with --token.
+fn backend_token() -> &'static str {
+    AUTH_TOKEN.get_or_init(|| match headless::token() {
+        Some(token) => token.to_string(),
+        None => uuid::Uuid::new_v4().simple().to_string(),
+    })
+}
+
+// Secret holding the token for the external backend set with set_backend_url
+const EXTERNAL_TOKEN_KEY: &str = "backend_token";
+
+// Token for an external backend: CHIKEN_BACKEND_TOKEN next to CHIKEN_BACKEND_URL,
+// otherwise the one saved by set_backend_url
+fn external_backend_token() -> Option<String> {
+    if let Some(token) = env::var("CHIKEN_BACKEND_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+    {
+        return Some(token);
+    }
+    secret_store::get_secret(EXTERNAL_TOKEN_KEY).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read the external backend token: {}", e);
+        None
+    })
+}
+
+// Token to send to the backend currently in use, bundled or external
+fn active_backend_token(app_handle: &tauri::AppHandle) -> Option<String> {
+    let external = app_handle
+        .try_state::<SidecarHandle>()
+        .is_some_and(|state| state.lock().unwrap().external_url.is_some());
+    if external {
+        external_backend_token()
+    } else {
+        Some(backend_token().to_string())
+    }
+}
+
+// Lifecycle states reported through the sidecar-status event
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SidecarLifecycle {
+    Starting,
+    Running,
+    Stopped,
+    Crashed,
+    // The sidecar could not be started at all
+    Error,
+}
+
+// Payload of the sidecar-status event
+#[derive(Clone, Serialize)]
+struct SidecarStatusEvent {
+    state: SidecarLifecycle,
+    pid: Option<u32>,
+    // Set on the stopped/crashed transitions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit: Option<SidecarExit>,
+    // Set on the error transition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn emit_sidecar_status(app_handle: &tauri::AppHandle, state: SidecarLifecycle, pid: Option<u32>) {
+    app_handle
+        .emit(
+            "sidecar-status",
+            SidecarStatusEvent {
+                state,
+                pid,
+                exit: None,
+                error: None,
+            },
+        )
+        .ok();
+}
+
+// Snapshot returned by the sidecar_status command
+#[derive(Clone, Serialize)]
+struct SidecarStatus {
+    running: bool,
+    // Not started yet because of backend.lazy_start
+    deferred: bool,
+    // Using an external backend, so no sidecar is running on purpose
+    external: bool,
+    // The backend in use, bundled or external, has answered
+    ready: bool,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+    restart_count: u32,
+    last_exit_code: Option<i32>,
+    last_health_check: Option<watchdog::HealthCheck>,
+}
+
+// Returned by get_app_info for the About page and bug reports
+#[derive(Clone, Serialize)]
+struct AppInfo {
+    version: String,
+    git_hash: &'static str,
+    // Unix seconds
+    build_timestamp: u64,
+    os: &'static str,
+    arch: &'static str,
+    // "debug" resolves the sidecar from the Python sources, "release" the bundled binary
+    build_profile: &'static str,
+    sidecar_path: Option<String>,
+}
+
+// Returned by get_app_version so the UI footer and bug reports name the exact build
+#[derive(Clone, Serialize)]
+struct AppVersion {
+    version: &'static str,
+    commit: &'static str,
+    // UTC, YYYY-MM-DD
+    build_date: &'static str,
+}
+
+// Notification requested by the backend through a NOTIFY_PREFIX line
+#[derive(Deserialize)]
+struct NotifyRequest {
+    title: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+// Completion marker sent through a JOB_DONE_PREFIX line
+#[derive(Deserialize)]
+struct JobDone {
+    job: Option<String>,
+    title: Option<String>,
+    body: Option<String>,
+    kind: Option<String>,
+}
+
+// Startup progress reported through a PROGRESS_PREFIX line, also the payload of
+// the sidecar-progress event
+#[derive(Clone, Serialize, Deserialize)]
+struct SidecarProgress {
+    stage: String,
+    #[serde(deserialize_with = "clamped_pct")]
+    pct: u8,
+}
+
+// Any JSON number is accepted, fractions rounded and out-of-range values
+// clamped to 0-100, so a backend overshooting to 300 still reads as done
+fn clamped_pct<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let pct = f64::deserialize(deserializer)?;
+    Ok(pct.clamp(0.0, 100.0).round() as u8)
+}
+
+// Payload of the second-instance-args event, for flows like "open file with ChiKen"
+#[derive(Clone, Serialize)]
+struct SecondInstanceArgs {
+    argv: Vec<String>,
+    cwd: String,
+}
+
+// Payload of the sidecar-startup-timeout event
+#[derive(Clone, Serialize)]
+struct SidecarStartupTimeout {
+    url: String,
+    timeout_secs: u64,
+    last_error: Option<String>,
+}
+
+// Payload of the sidecar-terminated event
+#[derive(Clone, Serialize)]
+struct SidecarTerminated {
+    code: Option<i32>,
+    signal: Option<i32>,
+    // False when the process died on its own rather than via shutdown/restart
+    intentional: bool,
+    stderr_tail: Vec<String>,
+}
 
 // TODO: change pyinstaller to --onedir. refs: https://github.com/tauri-apps/tauri/discussions/3273
 // Actual TODO: eliminate IPC using pytauri
 
+// Set while the window is in macOS simple fullscreen, which is_fullscreen
+// doesn't report
+#[cfg(target_os = "macos")]
+static SIMPLE_FULLSCREEN: AtomicBool = AtomicBool::new(false);
+
+// Switch fullscreen on or off, returning the new state. The request can fail on
+// some Wayland compositors, which is reported rather than panicking.
+fn toggle_window_fullscreen(window: &tauri::Window, simple: bool) -> Result<bool, String> {
+    #[cfg(target_os = "macos")]
+    if simple || SIMPLE_FULLSCREEN.load(Ordering::Relaxed) {
+        let fullscreen = !SIMPLE_FULLSCREEN.load(Ordering::Relaxed);
+        window
+            .set_simple_fullscreen(fullscreen)
+            .map_err(|e| format!("Failed to change fullscreen: {}", e))?;
+        SIMPLE_FULLSCREEN.store(fullscreen, Ordering::Relaxed);
+        return Ok(fullscreen);
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = simple;
+    let fullscreen = !window
+        .is_fullscreen()
+        .map_err(|e| format!("Failed to read fullscreen state: {}", e))?;
+    window
+        .set_fullscreen(fullscreen)
+        .map_err(|e| format!("Failed to change fullscreen: {}", e))?;
+    Ok(fullscreen)
+}
+
+// Returns whether the window is now fullscreen. `simple` picks macOS's simple
+// fullscreen, which stays on the current space and keeps the overlay titlebar
+// where it is, instead of the native one on its own space; other platforms
+// ignore it.
+#[tauri::command]
+fn toggle_fullscreen(window: tauri::Window, simple: Option<bool>) -> Result<bool, ChikenError> {
+    Ok(toggle_window_fullscreen(&window, simple.unwrap_or(false))?)
+}
+
+// Apply and persist the webview zoom, returning the factor actually used
 #[tauri::command]
-fn toggle_fullscreen(window: tauri::Window) {
-    if let Ok(is_fullscreen) = window.is_fullscreen() {
-        window.set_fullscreen(!is_fullscreen).unwrap();
+fn set_zoom(window: tauri::WebviewWindow, factor: f64) -> Result<f64, ChikenError> {
+    if !factor.is_finite() {
+        return Err(format!("Invalid zoom factor: {}", factor).into());
     }
+    let factor = factor.clamp(MIN_ZOOM, MAX_ZOOM);
+    window.set_zoom(factor).map_err(|e| e.to_string())?;
+    settings::set(window.app_handle(), "window.zoom", factor)?;
+    Ok(factor)
+}
+
+// The webview has no zoom getter, so this is the last factor set_zoom stored
+#[tauri::command]
+fn get_zoom(app_handle: tauri::AppHandle) -> f64 {
+    settings::get::<f64>(&app_handle, "window.zoom")
+        .map(|factor| factor.clamp(MIN_ZOOM, MAX_ZOOM))
+        .unwrap_or(1.0)
+}
+
+// Native desktop notification, e.g. when indexing finishes while the window is
+// in the background. The backend can do the same with @@notify@@ or @@JOB_DONE
+// stdout lines.
+#[tauri::command]
+fn notify(
+    app_handle: tauri::AppHandle,
+    title: String,
+    body: String,
+    kind: Option<String>,
+) -> Result<(), ChikenError> {
+    Ok(notifications::show(
+        &app_handle,
+        &title,
+        &body,
+        kind.as_deref(),
+    )?)
+}
+
+// Badge the macOS dock icon with a pending-job count, None clears it; a no-op on
+// other platforms
+#[tauri::command]
+fn set_dock_badge(app_handle: tauri::AppHandle, count: Option<u32>) -> Result<(), ChikenError> {
+    Ok(notifications::set_dock_badge(&app_handle, count)?)
+}
+
+#[tauri::command]
+fn get_app_info(app_handle: tauri::AppHandle) -> AppInfo {
+    AppInfo {
+        version: app_handle.package_info().version.to_string(),
+        git_hash: env!("CHIKEN_GIT_HASH"),
+        build_timestamp: env!("CHIKEN_BUILD_TIMESTAMP").parse().unwrap_or(0),
+        os: env::consts::OS,
+        arch: env::consts::ARCH,
+        build_profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        sidecar_path: get_sidecar_path(app_handle).ok(),
+    }
+}
+
+#[tauri::command]
+fn get_app_version() -> AppVersion {
+    AppVersion {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("CHIKEN_GIT_HASH"),
+        build_date: env!("CHIKEN_BUILD_DATE"),
+    }
+}
+
+// Report a sidecar that failed to start, both as a sidecar-status transition and
+// on the sidecar-spawn-error channel the connection manager listens to
+fn emit_sidecar_error(app_handle: &tauri::AppHandle, error: &str) {
+    tracing::error!("Failed to start sidecar: {}", error);
+    app_handle
+        .emit(
+            "sidecar-status",
+            SidecarStatusEvent {
+                state: SidecarLifecycle::Error,
+                pid: None,
+                exit: None,
+                error: Some(error.to_string()),
+            },
+        )
+        .ok();
+    app_handle.emit("sidecar-spawn-error", error).ok();
+}
+
+// Check that `path` is an executable file. `fix` is appended to the error to
+// tell the user what to do about it.
+fn check_executable(path: &Path, fix: &str) -> Result<(), String> {
+    let missing = || format!("backend binary not found at {} — {}", path.display(), fix);
+    let metadata = std::fs::metadata(path).map_err(|_| missing())?;
+    if !metadata.is_file() {
+        return Err(missing());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(format!(
+                "backend binary at {} is not executable — {}",
+                path.display(),
+                fix
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The shell plugin resolves sidecar("chicken-core") to a binary next to the app
+// executable. Check it up front so a broken install gets a clear message rather
+// than an opaque spawn error.
+fn check_sidecar_binary() -> Result<(), String> {
+    let exe = env::current_exe().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    let bin = match env::consts::OS {
+        "windows" => "chicken-core.exe",
+        _ => "chicken-core",
+    };
+    let path = exe
+        .parent()
+        .ok_or("Failed to get parent directory")?
+        .join(bin);
+    check_executable(&path, "reinstall ChiKen")
+}
+
+// Backend binary to run instead of the bundled one, e.g. a nuitka build:
+// CHIKEN_SIDECAR_PATH, otherwise the backend.sidecar_path setting. Returned
+// with where it came from, for logs and error messages.
+fn sidecar_path_override(app_handle: &tauri::AppHandle) -> Option<(&'static str, PathBuf)> {
+    if let Some(path) = env::var("CHIKEN_SIDECAR_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    {
+        return Some(("CHIKEN_SIDECAR_PATH", PathBuf::from(path.trim())));
+    }
+    settings::get::<String>(app_handle, "backend.sidecar_path")
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| {
+            (
+                "the backend.sidecar_path setting",
+                PathBuf::from(path.trim()),
+            )
+        })
 }
 
 // Command to get the absolute path to the sidecar binary
 #[tauri::command]
-fn get_sidecar_path(handle: tauri::AppHandle) -> Result<String, String> {
+fn get_sidecar_path(handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    if let Some((source, path)) = sidecar_path_override(&handle) {
+        check_executable(&path, &format!("check {}", source))?;
+        tracing::info!("Using sidecar path from {}: {}", source, path.display());
+        return Ok(path.to_string_lossy().to_string());
+    }
+
     // In development, use the Python source
     if cfg!(debug_assertions) {
         let repo_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
@@ -37,7 +601,7 @@ fn get_sidecar_path(handle: tauri::AppHandle) -> Result<String, String> {
             .canonicalize()
             .map_err(|e| format!("Failed to resolve dev sidecar path: {}", e))?;
         let path_str = sidecar_path.to_string_lossy().to_string();
-        println!("[tauri] Using development sidecar path: {}", path_str);
+        tracing::info!("Using development sidecar path: {}", path_str);
         return Ok(path_str);
     }
 
@@ -50,10 +614,7 @@ fn get_sidecar_path(handle: tauri::AppHandle) -> Result<String, String> {
         };
         let sidecar_path = resource_path.join(bin);
         if sidecar_path.exists() {
-            println!(
-                "[tauri] Using resource sidecar path: {}",
-                sidecar_path.display()
-            );
+            tracing::info!("Using resource sidecar path: {}", sidecar_path.display());
             return Ok(sidecar_path.to_string_lossy().to_string());
         }
     }
@@ -71,187 +632,2510 @@ fn get_sidecar_path(handle: tauri::AppHandle) -> Result<String, String> {
     };
     let path = app_dir.join(bin);
 
-    println!("[tauri] Using fallback sidecar path: {}", path.display());
+    tracing::info!("Using fallback sidecar path: {}", path.display());
     Ok(path.to_string_lossy().to_string())
 }
 
+// Where the running sidecar's pid is recorded so a crashed app run can be cleaned up
+fn sidecar_pid_file(app_handle: &tauri::AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join("sidecar.pid"))
+}
+
+// Data dir handed to the sidecar as CHIKEN_DATA_DIR, the app's own data dir, so
+// the backend and the app agree on where everything lives. Created here: a
+// backend that can't use it must not start and pick a location of its own.
+fn sidecar_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data dir: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create data dir {}: {}", dir.display(), e))?;
+    data_archive::migrate_legacy_backend_data(app_handle, &dir);
+    Ok(dir)
+}
+
+// Name given with --profile, None for the default profile
+#[tauri::command]
+fn get_current_profile() -> Option<String> {
+    profile::current().map(str::to_string)
+}
+
+// Data dir the backend uses, for the settings page
+#[tauri::command]
+fn get_data_dir(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    Ok(sidecar_data_dir(&app_handle)?.to_string_lossy().to_string())
+}
+
+fn sidecar_log_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_log_dir()
+        .map(|dir| dir.join(sidecar_log::LOG_FILE_NAME))
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))
+}
+
+// Ask the OS for a free TCP port. The listener is dropped before returning so the
+// port is released again by the time the sidecar binds it.
+fn pick_free_port() -> Result<u16, String> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .map_err(|e| format!("Failed to find a free port: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read free port: {}", e))?
+        .port();
+    Ok(port)
+}
+
+// Reuse the previous sidecar's port while it is still free so the backend URL stays
+// stable across restarts. The first spawn tries DEFAULT_SIDECAR_PORT, where
+// the frontend looks before it has asked for the URL; a random port is only
+// the fallback for when another process holds it.
+fn choose_sidecar_port(previous: Option<u16>) -> Result<u16, String> {
+    let preferred = previous.unwrap_or(DEFAULT_SIDECAR_PORT);
+    if TcpListener::bind(("127.0.0.1", preferred)).is_ok() {
+        return Ok(preferred);
+    }
+    tracing::info!("Backend port {} is in use, picking a new one.", preferred);
+    pick_free_port()
+}
+
+// Whether spawn_and_monitor_sidecar actually started a process
+enum SpawnOutcome {
+    Spawned,
+    AlreadyRunning,
+}
+
 // Helper function to spawn the sidecar and monitor its stdout/stderr
-fn spawn_and_monitor_sidecar(app_handle: tauri::AppHandle) -> Result<(), String> {
-    // Check if a sidecar process already exists
-    if let Some(state) = app_handle.try_state::<Arc<Mutex<Option<CommandChild>>>>() {
-        let child_process = state.lock().unwrap();
-        if child_process.is_some() {
+#[tracing::instrument(name = "sidecar_spawn", skip_all)]
+fn spawn_and_monitor_sidecar(app_handle: tauri::AppHandle) -> Result<SpawnOutcome, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Failed to access app state")?
+        .inner()
+        .clone();
+    let previous_port = {
+        let mut sidecar = state.lock().unwrap();
+        if !sidecar.reserve_spawn()? {
             // A sidecar is already running, do not spawn a new one
-            println!("[tauri] Sidecar is already running. Skipping spawn.");
-            return Ok(()); // Exit early since sidecar is already running
+            tracing::info!("Sidecar is already running or starting. Skipping spawn.");
+            return Ok(SpawnOutcome::AlreadyRunning); // Exit early since sidecar is already running
         }
-    }
+        sidecar.port
+    };
     // Spawn sidecar
-    let sidecar_command = app_handle
-        .shell()
-        .sidecar("chicken-core")
-        .map_err(|e| e.to_string())?
-        .env("PYTHONIOENCODING", "utf-8");
-    let (mut rx, child) = sidecar_command.spawn().map_err(|e| e.to_string())?;
+    emit_sidecar_status(&app_handle, SidecarLifecycle::Starting, None);
+    let path_override = sidecar_path_override(&app_handle);
+    let binary_checked = match &path_override {
+        Some((source, path)) => check_executable(path, &format!("check {}", source)),
+        None => check_sidecar_binary(),
+    };
+    let spawned = binary_checked
+        .and_then(|()| match headless::port() {
+            Some(port) => Ok(port),
+            None => choose_sidecar_port(previous_port),
+        })
+        .and_then(|port| {
+            // Secrets are read fresh on every spawn, so a restart picks up changed keys
+            let secret_env = secret_store::sidecar_env().unwrap_or_else(|e| {
+                tracing::warn!("Failed to read secrets for the sidecar: {}", e);
+                Vec::new()
+            });
+            tracing::info!(
+                "Passing {} secret(s) to the sidecar environment",
+                secret_env.len()
+            );
+            let data_dir = sidecar_data_dir(&app_handle)?;
+            let sidecar_command = match &path_override {
+                Some((source, path)) => {
+                    tracing::info!("Spawning sidecar {} from {}", path.display(), source);
+                    app_handle.shell().command(path)
+                }
+                None => {
+                    tracing::info!("Spawning the bundled sidecar");
+                    app_handle
+                        .shell()
+                        .sidecar("chicken-core")
+                        .map_err(|e| e.to_string())?
+                }
+            };
+            let sidecar_command = sidecar_command
+                .args(["--port", port.to_string().as_str()])
+                .envs(backend_env::sidecar_env(&app_handle))
+                .envs(secret_env)
+                // Applied last so nothing above can change them
+                .env("CHIKEN_PORT", port.to_string())
+                .env("CHIKEN_DATA_DIR", data_dir)
+                .env("PYTHONIOENCODING", "utf-8")
+                .env("CHIKEN_LOG_LEVEL", logging::level(&app_handle))
+                .env("CHIKEN_AUTH_TOKEN", backend_token());
+            let sidecar_command = match secret_store::device_id() {
+                Ok(device_id) => sidecar_command.env("CHIKEN_DEVICE_ID", device_id),
+                Err(e) => {
+                    tracing::warn!("Sidecar started without a device ID: {}", e);
+                    sidecar_command
+                }
+            };
+            let sidecar_command = match profile::current() {
+                Some(profile) => sidecar_command.env("CHIKEN_PROFILE", profile),
+                None => sidecar_command,
+            };
+            let sidecar_command = match headless::host() {
+                Some(host) => sidecar_command.args(["--host", host]),
+                None => sidecar_command,
+            };
+            let (rx, child) = sidecar_command.spawn().map_err(|e| e.to_string())?;
+            Ok((port, rx, child))
+        });
+    let (port, rx, child) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            state.lock().unwrap().spawning = false;
+            emit_sidecar_error(&app_handle, &e);
+            return Err(ChikenError::SidecarSpawnFailed { message: e });
+        }
+    };
+    let pid = child.pid();
+    if let Some(pid_file) = sidecar_pid_file(&app_handle) {
+        if let Err(e) = process_tree::write_pid_file(&pid_file, pid) {
+            tracing::warn!("{}", e);
+        }
+    }
 
     // IMPORTANT: Store the child process in the app state to keep stdin pipe open
     // The child handle must stay alive for the stdin pipe to remain connected
-    if let Some(state) = app_handle.try_state::<Arc<Mutex<Option<CommandChild>>>>() {
-        *state.lock().unwrap() = Some(child);
-        println!("[tauri] Sidecar spawned and child handle stored (stdin pipe active)");
-    } else {
-        return Err("Failed to access app state".to_string());
+    let generation = {
+        let mut sidecar = state.lock().unwrap();
+        sidecar.child = Some(child);
+        sidecar.spawning = false;
+        sidecar.generation += 1;
+        sidecar.running = true;
+        sidecar.stopping = false;
+        sidecar.pid = Some(pid);
+        sidecar.started_at = Some(Instant::now());
+        sidecar.port = Some(port);
+        sidecar.ready = false;
+        sidecar.last_health_check = None;
+        sidecar.generation
+    };
+    // Readiness, output, termination and crash restarts of this process are all
+    // logged under one span, so its whole life reads as one story in the log
+    let span = tracing::info_span!(parent: None, "sidecar", generation, pid, port);
+    span.in_scope(|| {
+        tracing::info!(
+            "Sidecar spawned on port {} and child handle stored (stdin pipe active)",
+            port
+        )
+    });
+    if previous_port != Some(port) {
+        app_handle
+            .emit("backend-url-changed", format!("http://localhost:{}", port))
+            .ok();
     }
 
-    // Spawn an async task to handle sidecar communication
-    tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes);
-                    println!("Sidecar stdout: {}", line);
-                    // Emit the line to the frontend
-                    app_handle
-                        .emit("sidecar-stdout", line.to_string())
-                        .expect("Failed to emit sidecar stdout event");
-                }
-                CommandEvent::Stderr(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes);
-                    eprintln!("Sidecar stderr: {}", line);
-                    // Emit the error line to the frontend
-                    app_handle
-                        .emit("sidecar-stderr", line.to_string())
-                        .expect("Failed to emit sidecar stderr event");
-                }
-                _ => {}
-            }
+    tauri::async_runtime::spawn(
+        wait_for_sidecar_ready(app_handle.clone(), state.clone(), generation, port)
+            .instrument(span.clone()),
+    );
+
+    let log_file = match sidecar_log_path(&app_handle)
+        .and_then(|path| sidecar_log::spawn_writer(path).map_err(|e| e.to_string()))
+    {
+        Ok(log_file) => Some(log_file),
+        Err(e) => {
+            tracing::warn!("Sidecar output will not be logged to file: {}", e);
+            sidecar_log::report_file_failure(&app_handle, &e);
+            None
         }
+    };
+    let mut secrets = secret_store::secret_values().unwrap_or_else(|e| {
+        tracing::warn!("Failed to read secrets for log redaction: {}", e);
+        Vec::new()
     });
+    // The backend could echo its environment, auth token included
+    secrets.push(backend_token().to_string());
+    let redactor = redact::Redactor::new(secrets);
+    let monitor = SidecarMonitor {
+        app_handle,
+        state,
+        generation,
+        pid,
+        log_file,
+        redactor,
+        stderr_tail: VecDeque::with_capacity(STDERR_TAIL_LINES),
+        stderr_levels: sidecar_log::LevelParser::default(),
+        stdout_batch: sidecar_log::StdoutBatch::new(STDOUT_BATCH_MAX_LINES, STDOUT_BATCH_INTERVAL),
+        reported_running: false,
+        crashed: false,
+    };
+    // Spawn an async task to handle sidecar communication
+    tauri::async_runtime::spawn(monitor.run(rx).instrument(span));
 
-    Ok(())
+    Ok(SpawnOutcome::Spawned)
 }
 
-// Define a command to shutdown sidecar process
-#[tauri::command]
-fn shutdown_sidecar(app_handle: tauri::AppHandle) -> Result<String, String> {
-    println!("[tauri] Received command to shutdown sidecar.");
-    // Access the sidecar process state
-    if let Some(state) = app_handle.try_state::<Arc<Mutex<Option<CommandChild>>>>() {
-        let mut child_process = state
-            .lock()
-            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+fn startup_timeout(app_handle: &tauri::AppHandle) -> Duration {
+    env::var("CHIKEN_STARTUP_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .or_else(|| settings::get::<u64>(app_handle, "backend.startup_timeout_secs"))
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STARTUP_TIMEOUT)
+}
 
-        if let Some(process) = child_process.take() {
-            // Attempt to gracefully terminate the process
-            match process.kill() {
-                Ok(_) => {
-                    println!("[tauri] Sidecar process terminated successfully.");
-                    Ok("Sidecar process terminated successfully.".to_string())
-                }
-                Err(err) => {
-                    println!("[tauri] Failed to kill sidecar process: {}", err);
-                    Err(format!("Failed to kill sidecar process: {}", err))
-                }
+// Poll GET /health on `url` until it answers, or quietly give up once `current`
+// reports that the backend being waited for has been replaced or stopped. Past
+// the startup timeout the stall is reported with a sidecar-startup-timeout event
+// and a dialog, but polling goes on: the process is left running, since a slow
+// first start (a model download, say) may still finish.
+async fn wait_until_healthy(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+    current: impl Fn() -> bool,
+) -> bool {
+    let timeout = startup_timeout(app_handle);
+    let deadline = Instant::now() + timeout;
+    let mut last_error = None;
+    let mut reported = false;
+    loop {
+        if !current() {
+            return false;
+        }
+        match probe_backend_health(url, active_backend_token(app_handle).as_deref()).await {
+            HealthProbe::Healthy => return true,
+            HealthProbe::Unreachable(reason) => last_error = Some(reason),
+            HealthProbe::Unhealthy(status) => last_error = Some(format!("HTTP {}", status)),
+        }
+        if !reported && Instant::now() >= deadline {
+            reported = true;
+            tracing::error!(
+                "Backend at {} did not become ready within {:?}: {:?}",
+                url,
+                timeout,
+                last_error
+            );
+            app_handle
+                .emit(
+                    "sidecar-startup-timeout",
+                    SidecarStartupTimeout {
+                        url: url.to_string(),
+                        timeout_secs: timeout.as_secs(),
+                        last_error: last_error.clone(),
+                    },
+                )
+                .ok();
+            if !headless::enabled() {
+                offer_view_logs(app_handle, timeout);
             }
-        } else {
-            println!("[tauri] No active sidecar process to shutdown.");
-            Err("No active sidecar process to shutdown.".to_string())
         }
-    } else {
-        Err("Sidecar process state not found.".to_string())
+        tokio::time::sleep(READINESS_POLL_INTERVAL).await;
     }
 }
 
-// Define a command to start sidecar process.
-#[tauri::command]
-fn start_sidecar(app_handle: tauri::AppHandle) -> Result<String, String> {
-    println!("[tauri] Received command to start sidecar.");
-    spawn_and_monitor_sidecar(app_handle)?;
-    Ok("Sidecar spawned and monitoring started.".to_string())
+fn offer_view_logs(app_handle: &tauri::AppHandle, timeout: Duration) {
+    let log_handle = app_handle.clone();
+    app_handle
+        .dialog()
+        .message(format!(
+            "The backend hasn't finished starting after {} seconds. It is still running \
+             and may yet come up; its log shows what it is doing.",
+            timeout.as_secs()
+        ))
+        .title("ChiKen backend is slow to start")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "View logs".to_string(),
+            "Keep waiting".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                if let Err(e) = open_log_folder(log_handle) {
+                    tracing::warn!("{}", e);
+                }
+            }
+        });
 }
 
-// Secret store commands
-#[tauri::command]
-fn set_secret(value: String) -> Result<(), String> {
-    secret_store::set_secret(&value)
+// Called once per backend, after `ready` was set under the lock
+fn on_backend_ready(app_handle: &tauri::AppHandle, url: String) {
+    tracing::info!("Backend is ready at {}", url);
+    app_handle.emit("backend-ready", &url).ok();
+    app_handle.emit("sidecar-ready", url).ok();
+    splash::finish(app_handle);
 }
 
-#[tauri::command]
-fn get_secret() -> Result<Option<String>, String> {
-    secret_store::get_secret()
+// Resolve once `ready` is set, waiting for backend-ready for up to `timeout`
+async fn wait_until_ready(
+    app_handle: &tauri::AppHandle,
+    state: &SidecarHandle,
+    timeout: Duration,
+) -> Result<(), ChikenError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    // Listening before checking the flag so an event in between isn't missed
+    let listener = app_handle.once("backend-ready", move |_| {
+        tx.send(()).ok();
+    });
+    {
+        let sidecar = state.lock().unwrap();
+        if sidecar.ready || sidecar.deferred {
+            app_handle.unlisten(listener);
+            // Waiting is pointless until something calls start_sidecar
+            return if sidecar.ready {
+                Ok(())
+            } else {
+                Err(ChikenError::SidecarNotRunning)
+            };
+        }
+    }
+    if tokio::time::timeout(timeout, rx).await.is_err() {
+        app_handle.unlisten(listener);
+        return Err(format!(
+            "Backend did not become ready within {} seconds.",
+            timeout.as_secs()
+        )
+        .into());
+    }
+    Ok(())
 }
 
-#[tauri::command]
-fn get_backend_url() -> Result<String, String> {
-    // TODO: spawn on random port
-    Ok("http://localhost:8009".to_string())
+// Wait for the freshly spawned sidecar to answer, then emit sidecar-ready
+async fn wait_for_sidecar_ready(
+    app_handle: tauri::AppHandle,
+    state: SidecarHandle,
+    generation: u64,
+    port: u16,
+) {
+    let url = format!("http://localhost:{}", port);
+    // Stops polling once the backend announced itself with READY_LINE
+    let current = || {
+        let sidecar = state.lock().unwrap();
+        sidecar.generation == generation && sidecar.running && !sidecar.ready
+    };
+    if !wait_until_healthy(&app_handle, &url, current).await {
+        return;
+    }
+    {
+        let mut sidecar = state.lock().unwrap();
+        if sidecar.generation != generation || sidecar.ready {
+            return;
+        }
+        sidecar.ready = true;
+    }
+    on_backend_ready(&app_handle, url);
 }
 
-fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_store::Builder::new().build())
-        .plugin(tauri_plugin_window_state::Builder::new().build())
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_decorum::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
-            // Store the initial sidecar process in the app state
-            app.manage(Arc::new(Mutex::new(None::<CommandChild>)));
-            // Clone the app handle for use elsewhere
-            let app_handle = app.handle().clone();
-            // Spawn the Python sidecar on startup
-            println!("[tauri] Creating sidecar...");
-            spawn_and_monitor_sidecar(app_handle).ok();
-            println!("[tauri] Sidecar spawned and monitoring started.");
+// The same readiness handshake for an external backend, so the splash,
+// sidecar-ready and get_backend_url behave as they do with the bundled sidecar
+async fn wait_for_external_ready(app_handle: tauri::AppHandle, state: SidecarHandle, url: String) {
+    let current = || state.lock().unwrap().external_url.as_deref() == Some(url.as_str());
+    if !wait_until_healthy(&app_handle, &url, current).await {
+        return;
+    }
+    {
+        let mut sidecar = state.lock().unwrap();
+        if sidecar.external_url.as_deref() != Some(url.as_str()) {
+            return;
+        }
+        sidecar.ready = true;
+    }
+    on_backend_ready(&app_handle, url);
+}
 
-            // Create a custom titlebar for main window
-            // On Windows this will hide decoration and render custom window controls
-            // On macOS it expects a hiddenTitle: true and titleBarStyle: overlay
-            let main_window = app.get_webview_window("main").unwrap();
-            main_window
-                .create_overlay_titlebar()
-                .expect("[tauri] Failed to create overlay titlebar");
+// A protocol line from the backend, see the *_PREFIX constants
+enum ControlLine {
+    Ready,
+    // Also what a JOB_DONE_PREFIX line turns into
+    Notify(NotifyRequest),
+    Progress(SidecarProgress),
+    // None clears the badge
+    Badge(Option<u32>),
+    // A known prefix whose payload didn't parse; `kind` names the line in the
+    // warning
+    Malformed { kind: &'static str, error: String },
+}
 
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            start_sidecar,
-            shutdown_sidecar,
-            toggle_fullscreen,
-            get_sidecar_path,
-            set_secret,
-            get_secret,
-            get_backend_url,
-        ])
-        .build(tauri::generate_context!())
-        .expect("Error while running tauri application")
-        .run(|app_handle, event| match event {
-            RunEvent::ExitRequested { .. } => {
-                println!("[tauri] App exit requested. Attempting to shutdown sidecar...");
-                if let Err(e) = app_handle.save_window_state(StateFlags::all()) {
-                    println!("[tauri] Failed to save window state: {}", e);
-                }
+// None for ordinary output
+fn parse_control_line(line: &str) -> Option<ControlLine> {
+    let control = if line == READY_LINE {
+        ControlLine::Ready
+    } else if let Some(json) = line.strip_prefix(NOTIFY_PREFIX) {
+        match serde_json::from_str::<NotifyRequest>(json) {
+            Ok(request) => ControlLine::Notify(request),
+            Err(e) => ControlLine::Malformed {
+                kind: "notify",
+                error: e.to_string(),
+            },
+        }
+    } else if let Some(json) = line.strip_prefix(JOB_DONE_PREFIX) {
+        // Every field is optional, a bare "@@JOB_DONE {}" still notifies
+        match serde_json::from_str::<JobDone>(json) {
+            Ok(job) => ControlLine::Notify(NotifyRequest {
+                title: job.title.unwrap_or_else(|| "ChiKen".to_string()),
+                body: job
+                    .body
+                    .or_else(|| job.job.map(|name| format!("{} finished", name)))
+                    .unwrap_or_else(|| "A background job finished".to_string()),
+                kind: job.kind.or_else(|| Some("success".to_string())),
+            }),
+            Err(e) => ControlLine::Malformed {
+                kind: "JOB_DONE",
+                error: e.to_string(),
+            },
+        }
+    } else if let Some(json) = line.strip_prefix(PROGRESS_PREFIX) {
+        match serde_json::from_str::<SidecarProgress>(json) {
+            Ok(progress) => ControlLine::Progress(progress),
+            Err(e) => ControlLine::Malformed {
+                kind: "progress",
+                error: e.to_string(),
+            },
+        }
+    } else if let Some(count) = line.strip_prefix(BADGE_PREFIX) {
+        let count = count.trim();
+        match count.parse::<u32>() {
+            Ok(count) => ControlLine::Badge(Some(count)),
+            Err(_) if count.is_empty() => ControlLine::Badge(None),
+            Err(e) => ControlLine::Malformed {
+                kind: "badge",
+                error: e.to_string(),
+            },
+        }
+    } else {
+        return None;
+    };
+    Some(control)
+}
 
-                // Try to gracefully shutdown the sidecar
-                if let Some(state) = app_handle.try_state::<Arc<Mutex<Option<CommandChild>>>>() {
-                    let mut child_process = state.lock().unwrap();
-                    if let Some(process) = child_process.take() {
-                        match process.kill() {
-                            Ok(_) => {
-                                println!("[tauri] Sidecar terminated successfully on app exit")
-                            }
-                            Err(e) => {
-                                println!("[tauri] Failed to terminate sidecar on app exit: {}", e)
-                            }
-                        }
-                    } else {
-                        println!("[tauri] No active sidecar to terminate");
+// Per-process state of the task that consumes a sidecar's CommandEvents
+struct SidecarMonitor {
+    app_handle: tauri::AppHandle,
+    state: SidecarHandle,
+    generation: u64,
+    pid: u32,
+    log_file: Option<Sender<sidecar_log::LogLine>>,
+    // Applied to every line before it is printed, logged or emitted
+    redactor: redact::Redactor,
+    stderr_tail: VecDeque<String>,
+    // Tracks multi-line records so traceback lines share their header's level
+    stderr_levels: sidecar_log::LevelParser,
+    // Stdout lines not yet sent to the frontend
+    stdout_batch: sidecar_log::StdoutBatch,
+    reported_running: bool,
+    // Only a non-zero (or signal) exit that nobody asked for counts as a crash
+    crashed: bool,
+}
+
+impl SidecarMonitor {
+    async fn run(mut self, mut rx: Receiver<CommandEvent>) {
+        loop {
+            let event = match self.stdout_batch.deadline() {
+                Some(deadline) => tokio::select! {
+                    event = rx.recv() => event,
+                    _ = tokio::time::sleep_until(deadline.into()) => {
+                        self.flush_stdout();
+                        continue;
                     }
-                } else {
-                    println!("[tauri] Sidecar state not found during exit");
+                },
+                None => rx.recv().await,
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                CommandEvent::Stdout(line_bytes) => {
+                    let line = self.decode_line(&line_bytes);
+                    self.on_stdout(line)
                 }
+                CommandEvent::Stderr(line_bytes) => {
+                    let line = self.decode_line(&line_bytes);
+                    self.on_stderr(line)
+                }
+                CommandEvent::Error(err) => {
+                    tracing::warn!("Sidecar error: {}", err);
+                }
+                CommandEvent::Terminated(payload) => self.on_terminated(payload),
+                _ => {}
             }
-            _ => {}
-        });
+        }
+        self.flush_stdout();
+        self.finish().await;
+    }
+
+    fn decode_line(&self, line_bytes: &[u8]) -> String {
+        self.redactor
+            .redact(&String::from_utf8_lossy(line_bytes))
+            .into_owned()
+    }
+
+    fn write_log(&self, stream: &'static str, line: &str) {
+        let entry = sidecar_log::LogLine {
+            stream,
+            line: line.to_string(),
+        };
+        let log_buffer = self.app_handle.try_state::<sidecar_log::LogBuffer>();
+        sidecar_log::record(self.log_file.as_ref(), log_buffer.as_deref(), entry);
+    }
+
+    fn on_stdout(&mut self, line: String) {
+        tracing::debug!(target: "sidecar", "stdout: {}", line);
+        // Stdout carries no level and counts as info
+        let enabled = logging::sidecar_enabled(sidecar_log::LogLevel::Info);
+        if enabled {
+            self.write_log("stdout", &line);
+        }
+        if !self.reported_running {
+            self.reported_running = true;
+            emit_sidecar_status(&self.app_handle, SidecarLifecycle::Running, Some(self.pid));
+        }
+        if let Some(batch) = self.stdout_batch.before_line(&line) {
+            self.emit_stdout(batch);
+        }
+        if self.on_control_line(line.trim_end()) || !enabled {
+            return;
+        }
+        // Lines are sent to the frontend in batches; one IPC event per line
+        // freezes the UI when the backend logs heavily during ingestion
+        if let Some(batch) = self.stdout_batch.push(line, Instant::now()) {
+            self.emit_stdout(batch);
+        }
+    }
+
+    // Handle a protocol line from the backend, returning false for ordinary output
+    fn on_control_line(&mut self, line: &str) -> bool {
+        let Some(control) = parse_control_line(line) else {
+            return false;
+        };
+        match control {
+            ControlLine::Ready => self.on_ready(),
+            ControlLine::Notify(request) => self.notify(&request),
+            ControlLine::Progress(progress) => {
+                self.app_handle.emit("sidecar-progress", progress).ok();
+            }
+            ControlLine::Badge(count) => self.set_dock_badge(count),
+            ControlLine::Malformed { kind, error } => {
+                tracing::warn!("Ignoring malformed {} line from sidecar: {}", kind, error)
+            }
+        }
+        true
+    }
+
+    fn on_ready(&self) {
+        let port = {
+            let mut sidecar = self.state.lock().unwrap();
+            if sidecar.generation != self.generation || sidecar.ready {
+                return;
+            }
+            sidecar.ready = true;
+            sidecar.port
+        };
+        if let Some(port) = port {
+            on_backend_ready(&self.app_handle, format!("http://localhost:{}", port));
+        }
+    }
+
+    fn set_dock_badge(&self, count: Option<u32>) {
+        if let Err(e) = notifications::set_dock_badge(&self.app_handle, count) {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    fn notify(&self, request: &NotifyRequest) {
+        if let Err(e) = notifications::show(
+            &self.app_handle,
+            &request.title,
+            &request.body,
+            request.kind.as_deref(),
+        ) {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    fn on_stderr(&mut self, line: String) {
+        tracing::debug!(target: "sidecar", "stderr: {}", line);
+        // The tail for crash reports keeps every line whatever the log level
+        if self.stderr_tail.len() == STDERR_TAIL_LINES {
+            self.stderr_tail.pop_front();
+        }
+        self.stderr_tail.push_back(line.clone());
+        if let Some(history) = self.app_handle.try_state::<sidecar_log::StderrHistory>() {
+            history.push(&line);
+        }
+        let level = self.stderr_levels.parse(&line);
+        if !logging::sidecar_enabled(level) {
+            return;
+        }
+        self.write_log("stderr", &line);
+        // Stderr goes out immediately, after any stdout received before it
+        self.flush_stdout();
+        self.app_handle
+            .emit(
+                "sidecar-log",
+                sidecar_log::LevelledLine {
+                    level,
+                    message: line.clone(),
+                },
+            )
+            .ok();
+        self.app_handle.emit("sidecar-stderr", line).ok();
+    }
+
+    fn flush_stdout(&mut self) {
+        if let Some(batch) = self.stdout_batch.take() {
+            self.emit_stdout(batch);
+        }
+    }
+
+    fn emit_stdout(&self, batch: Vec<String>) {
+        self.app_handle.emit("sidecar-stdout-batch", batch).ok();
+    }
+
+    fn on_terminated(&mut self, payload: TerminatedPayload) {
+        tracing::info!(
+            "Sidecar terminated (code: {:?}, signal: {:?})",
+            payload.code,
+            payload.signal
+        );
+        self.flush_stdout();
+        let exit = SidecarExit {
+            code: payload.code,
+            signal: payload.signal,
+        };
+        let stderr_tail = self
+            .app_handle
+            .try_state::<sidecar_log::StderrHistory>()
+            .map(|history| history.snapshot())
+            .unwrap_or_default();
+        let exited_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let intentional = {
+            let mut sidecar = self.state.lock().unwrap();
+            let intentional = sidecar.stopping;
+            if sidecar.generation == self.generation {
+                sidecar.last_exit = Some(exit);
+                sidecar.last_exit_report = Some(SidecarExitReport {
+                    pid: self.pid,
+                    code: payload.code,
+                    signal: payload.signal,
+                    exited_at,
+                    intentional,
+                    stderr_tail,
+                });
+            }
+            intentional
+        };
+        self.crashed = !intentional && payload.code != Some(0);
+        let lifecycle = if self.crashed {
+            SidecarLifecycle::Crashed
+        } else {
+            SidecarLifecycle::Stopped
+        };
+        self.app_handle
+            .emit(
+                "sidecar-status",
+                SidecarStatusEvent {
+                    state: lifecycle,
+                    pid: Some(self.pid),
+                    exit: Some(exit),
+                    error: None,
+                },
+            )
+            .ok();
+        self.app_handle
+            .emit(
+                "sidecar-terminated",
+                SidecarTerminated {
+                    code: payload.code,
+                    signal: payload.signal,
+                    intentional,
+                    stderr_tail: self.stderr_tail.iter().cloned().collect(),
+                },
+            )
+            .ok();
+    }
+
+    // The event channel closes once the process is gone; drop the stale handle
+    // unless a newer sidecar has already replaced it
+    async fn finish(self) {
+        {
+            let mut sidecar = self.state.lock().unwrap();
+            if sidecar.generation != self.generation {
+                return;
+            }
+            sidecar.child = None;
+            sidecar.pid = None;
+            sidecar.running = false;
+            sidecar.ready = false;
+            if let Some(pid_file) = sidecar_pid_file(&self.app_handle) {
+                std::fs::remove_file(pid_file).ok();
+            }
+        }
+        if self.crashed {
+            restart_crashed_sidecar(self.app_handle, self.state).await;
+        }
+    }
+}
+
+// Backoff before the given (1-based) automatic restart attempt: 1s, 2s, 4s, ... capped
+fn sidecar_restart_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.saturating_sub(1).min(5)).min(SIDECAR_RESTART_BACKOFF_MAX)
+}
+
+// Respawn a sidecar that exited without being asked to, backing off between attempts
+#[tracing::instrument(name = "sidecar_restart", skip_all)]
+async fn restart_crashed_sidecar(app_handle: tauri::AppHandle, state: SidecarHandle) {
+    loop {
+        let attempt = state.lock().unwrap().next_restart_attempt(Instant::now());
+        let Some(attempt) = attempt else {
+            tracing::error!(
+                "Sidecar was restarted {} times within {:?}, giving up.",
+                MAX_SIDECAR_RESTARTS,
+                SIDECAR_RESTART_WINDOW
+            );
+            app_handle.emit("sidecar-failed", MAX_SIDECAR_RESTARTS).ok();
+            return;
+        };
+
+        let delay = sidecar_restart_delay(attempt);
+        tracing::info!(
+            "Sidecar exited unexpectedly, restarting in {:?} (attempt {}/{})",
+            delay,
+            attempt,
+            MAX_SIDECAR_RESTARTS
+        );
+        tokio::time::sleep(delay).await;
+
+        if state.lock().unwrap().stopping {
+            tracing::info!("Sidecar was stopped during backoff, not restarting.");
+            return;
+        }
+        match spawn_and_monitor_sidecar(app_handle.clone()) {
+            Ok(_) => return,
+            Err(e) => tracing::error!("Failed to restart sidecar: {}", e),
+        }
+    }
+}
+
+// Wait until the sidecar spawned as `generation` has been observed exiting
+async fn wait_for_sidecar_exit(
+    state: &SidecarHandle,
+    generation: u64,
+    timeout: Duration,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        {
+            let sidecar = state.lock().unwrap();
+            if sidecar.generation != generation || !sidecar.running {
+                return Ok(());
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err("Timed out waiting for sidecar process to exit.".to_string());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn shutdown_timeout(app_handle: &tauri::AppHandle) -> Duration {
+    settings::get::<u64>(app_handle, "backend.shutdown_timeout_secs")
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT)
+}
+
+// How the sidecar went away during stop_sidecar_gracefully
+#[derive(Debug, Clone, Copy)]
+enum ShutdownPath {
+    Graceful,
+    Killed,
+}
+
+// Ask the sidecar to exit on its own so the backend can flush its vector store,
+// and only kill it if it is still alive after `timeout`. The backend is sent a
+// shutdown command line and then has its stdin closed by dropping the
+// CommandChild, which its stdin monitor treats as a shutdown request; on Unix it
+// additionally receives SIGTERM. Returns None if no sidecar was running.
+#[tracing::instrument(name = "sidecar_stop", skip_all)]
+async fn stop_sidecar_gracefully(
+    state: &SidecarHandle,
+    timeout: Duration,
+) -> Result<Option<ShutdownPath>, String> {
+    let (mut process, generation) = {
+        let mut sidecar = state
+            .lock()
+            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+        // Deliberate stop: keep the monitor task from auto-restarting it
+        sidecar.stopping = true;
+        match sidecar.child.take() {
+            Some(process) => (process, sidecar.generation),
+            None => return Ok(None),
+        }
+    };
+
+    let pid = process.pid();
+    if let Err(e) = process.write(b"{\"cmd\":\"shutdown\"}\n") {
+        tracing::warn!("Failed to send shutdown command to sidecar: {}", e);
+    }
+    drop(process);
+    #[cfg(unix)]
+    process_tree::terminate(pid);
+    if wait_for_sidecar_exit(state, generation, timeout)
+        .await
+        .is_ok()
+    {
+        return Ok(Some(ShutdownPath::Graceful));
+    }
+
+    tracing::info!("Sidecar did not exit within {:?}, killing it.", timeout);
+    process_tree::kill_tree(pid);
+    wait_for_sidecar_exit(state, generation, SIDECAR_EXIT_TIMEOUT).await?;
+    Ok(Some(ShutdownPath::Killed))
+}
+
+// Define a command to shutdown sidecar process. `timeout_secs` bounds how long
+// the backend gets to exit on its own before it is killed.
+#[tauri::command]
+async fn shutdown_sidecar(
+    app_handle: tauri::AppHandle,
+    timeout_secs: Option<u64>,
+) -> Result<String, ChikenError> {
+    tracing::info!("Received command to shutdown sidecar.");
+    // Access the sidecar process state
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+    // An external backend isn't ours to stop
+    if let Some(url) = state.lock().unwrap().external_url.clone() {
+        return Err(ChikenError::ExternalBackend { url });
+    }
+
+    // The monitor task reports the final "stopped" status once the process is gone
+    let timeout = timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| shutdown_timeout(&app_handle));
+    match stop_sidecar_gracefully(&state, timeout).await {
+        Ok(Some(ShutdownPath::Graceful)) => {
+            tracing::info!("Sidecar process exited gracefully.");
+            Ok("Sidecar process exited gracefully.".to_string())
+        }
+        Ok(Some(ShutdownPath::Killed)) => {
+            tracing::info!("Sidecar process was killed after the shutdown timeout.");
+            Ok("Sidecar process did not exit in time and was killed.".to_string())
+        }
+        Ok(None) => {
+            tracing::info!("No active sidecar process to shutdown.");
+            emit_sidecar_status(&app_handle, SidecarLifecycle::Stopped, None);
+            Err(ChikenError::SidecarNotRunning)
+        }
+        Err(err) => {
+            tracing::error!("Failed to shut down sidecar process: {}", err);
+            Err(format!("Failed to shut down sidecar process: {}", err).into())
+        }
+    }
+}
+
+// Define a command to start sidecar process.
+#[tauri::command]
+fn start_sidecar(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    tracing::info!("Received command to start sidecar.");
+    match spawn_and_monitor_sidecar(app_handle)? {
+        SpawnOutcome::Spawned => Ok("Sidecar spawned and monitoring started.".to_string()),
+        SpawnOutcome::AlreadyRunning => Ok("Sidecar is already running or starting.".to_string()),
+    }
+}
+
+// Gracefully stop the running sidecar (if any) and spawn a fresh one
+async fn cycle_sidecar(
+    app_handle: &tauri::AppHandle,
+    state: &SidecarHandle,
+) -> Result<(), ChikenError> {
+    match stop_sidecar_gracefully(state, shutdown_timeout(app_handle)).await? {
+        Some(path) => tracing::info!("Previous sidecar exited ({:?}).", path),
+        None => tracing::info!("No active sidecar process, spawning a fresh one."),
+    }
+    spawn_and_monitor_sidecar(app_handle.clone())?;
+    Ok(())
+}
+
+// Define a command to stop the running sidecar (if any) and spawn a fresh one,
+// e.g. after settings that only take effect on backend startup have changed.
+#[tauri::command]
+async fn restart_sidecar(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    tracing::info!("Received command to restart sidecar.");
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+
+    {
+        let mut sidecar = state
+            .lock()
+            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+        if sidecar.restarting {
+            return Err("A sidecar restart is already in progress.".into());
+        }
+        sidecar.restarting = true;
+        sidecar.reset_restarts();
+    }
+    let result = cycle_sidecar(&app_handle, &state).await;
+    state.lock().unwrap().restarting = false;
+    result?;
+
+    app_handle
+        .emit("sidecar-restarted", ())
+        .map_err(|e| e.to_string())?;
+    Ok("Sidecar restarted.".to_string())
+}
+
+// Outcome of a single GET /health probe against the backend
+enum HealthProbe {
+    Healthy,
+    // Connection refused or timed out, usually because the backend isn't listening yet
+    Unreachable(String),
+    // The backend answered but with a non-success status
+    Unhealthy(u16),
+}
+
+async fn probe_backend_health(base_url: &str, token: Option<&str>) -> HealthProbe {
+    let request = reqwest::Client::new()
+        .get(format!("{}/health", base_url))
+        .timeout(HEALTH_CHECK_TIMEOUT);
+    let request = match token {
+        Some(token) => request.header(AUTH_TOKEN_HEADER, token),
+        None => request,
+    };
+    match request.send().await {
+        Ok(response) if response.status().is_success() => HealthProbe::Healthy,
+        Ok(response) => HealthProbe::Unhealthy(response.status().as_u16()),
+        Err(e) => HealthProbe::Unreachable(e.to_string()),
+    }
+}
+
+// Token the frontend must send as X-Chiken-Token with every backend request;
+// None for an external backend configured without one
+#[tauri::command]
+fn get_backend_token(app_handle: tauri::AppHandle) -> Option<String> {
+    active_backend_token(&app_handle)
+}
+
+// Ping the backend: Ok(false) while it isn't accepting connections yet, an error
+// when it is up but reports itself unhealthy
+#[tauri::command]
+async fn sidecar_health(app_handle: tauri::AppHandle) -> Result<bool, ChikenError> {
+    let Ok(url) = backend_url(&app_handle) else {
+        return Ok(false);
+    };
+    match probe_backend_health(&url, active_backend_token(&app_handle).as_deref()).await {
+        HealthProbe::Healthy => Ok(true),
+        HealthProbe::Unreachable(_) => Ok(false),
+        HealthProbe::Unhealthy(status) => {
+            Err(format!("Backend is running but unhealthy (HTTP {}).", status).into())
+        }
+    }
+}
+
+// Secret store commands
+#[tauri::command]
+fn set_secret(key: String, value: String) -> Result<(), ChikenError> {
+    secret_store::set_secret(&key, &value)
+}
+
+// Like set_secret, but for a provider's API key (e.g. "openai"): the format is
+// checked first and the key is stored under the provider's env var name, which
+// is returned
+#[tauri::command]
+fn set_validated_secret(provider: String, value: String) -> Result<String, ChikenError> {
+    let key = api_keys::set_validated_secret(&provider, &value)?;
+    Ok(key.to_string())
+}
+
+// Check a pasted key against the provider before it is saved, so the settings
+// UI can tell right away whether it works
+#[tauri::command]
+async fn test_api_key(provider: String, key: String) -> Result<bool, ChikenError> {
+    api_keys::test_api_key(&provider, &key).await
+}
+
+// Check the stored key for a provider, e.g. after a failed chat. `base_url`
+// points the check at an OpenAI-compatible server instead of the provider.
+#[tauri::command]
+async fn verify_api_key(
+    provider: String,
+    base_url: Option<String>,
+) -> Result<api_keys::KeyVerification, ChikenError> {
+    api_keys::verify_api_key(&provider, base_url.as_deref()).await
+}
+
+// Verbosity of the app log and of the sidecar lines logged and forwarded to the
+// frontend: "error", "warn", "info" or "debug". Persisted; the backend picks it
+// up as CHIKEN_LOG_LEVEL the next time it starts.
+#[tauri::command]
+fn set_log_level(app_handle: tauri::AppHandle, level: String) -> Result<(), ChikenError> {
+    Ok(logging::set_level(&app_handle, &level)?)
+}
+
+#[tauri::command]
+fn get_log_level(app_handle: tauri::AppHandle) -> String {
+    logging::level(&app_handle)
+}
+
+#[tauri::command]
+fn get_secret(key: String) -> Result<Option<String>, ChikenError> {
+    secret_store::get_secret(&key)
+}
+
+#[tauri::command]
+fn delete_secret(key: String) -> Result<(), ChikenError> {
+    secret_store::delete_secret(&key)
+}
+
+// Lets the UI show "API key configured" without the plaintext crossing IPC
+#[tauri::command]
+fn has_secret(key: String) -> Result<bool, ChikenError> {
+    secret_store::has_secret(&key)
+}
+
+// Re-read secrets from the keyring on next use, for when they were changed
+// outside the app (e.g. in Keychain Access)
+#[tauri::command]
+fn refresh_secrets_cache() {
+    secret_store::refresh_cache()
+}
+
+// Full reset, e.g. on sign out. Keys that couldn't be removed are listed in the
+// report instead of failing the call.
+#[tauri::command]
+fn clear_all_secrets() -> secret_store::ClearReport {
+    secret_store::clear_all_secrets()
+}
+
+// Which storage backend holds the secrets, so the UI can warn when it's the
+// weaker encrypted file rather than the OS keyring
+#[tauri::command]
+fn secret_backend_info() -> secret_store::BackendInfo {
+    secret_store::backend_info()
+}
+
+// Whether CHIKEN_PORTABLE is on, so the UI can warn that secrets are kept in a
+// file next to the app rather than in the OS keyring
+#[tauri::command]
+fn is_portable_mode() -> bool {
+    secret_store::is_portable()
+}
+
+// Anonymous per-install identifier, also passed to the sidecar as CHIKEN_DEVICE_ID
+#[tauri::command]
+fn get_device_id() -> Result<String, ChikenError> {
+    secret_store::device_id()
+}
+
+// Names of the stored secrets, never their values
+#[tauri::command]
+fn list_secrets() -> Result<Vec<String>, ChikenError> {
+    secret_store::list_secret_keys()
+}
+
+#[tauri::command]
+fn sidecar_status(app_handle: tauri::AppHandle) -> Result<SidecarStatus, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?;
+    let sidecar = state
+        .lock()
+        .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+    Ok(SidecarStatus {
+        running: sidecar.running,
+        deferred: sidecar.deferred,
+        external: sidecar.external_url.is_some(),
+        ready: sidecar.ready,
+        pid: sidecar.pid,
+        uptime_secs: sidecar
+            .started_at
+            .filter(|_| sidecar.running)
+            .map(|started| started.elapsed().as_secs()),
+        restart_count: sidecar.restart_count,
+        last_exit_code: sidecar.last_exit.and_then(|exit| exit.code),
+        last_health_check: sidecar.last_health_check.clone(),
+    })
+}
+
+// Memory and CPU use of the sidecar and its child processes, for a resource meter
+// polled a few times a minute
+#[tauri::command]
+async fn sidecar_metrics(
+    app_handle: tauri::AppHandle,
+) -> Result<process_tree::ProcessMetrics, ChikenError> {
+    let pid = {
+        let state = app_handle
+            .try_state::<SidecarHandle>()
+            .ok_or("Sidecar process state not found.")?;
+        let sidecar = state
+            .lock()
+            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+        sidecar
+            .pid
+            .filter(|_| sidecar.running)
+            .ok_or(ChikenError::SidecarNotRunning)?
+    };
+    let metrics = tauri::async_runtime::spawn_blocking(move || {
+        let sampler = app_handle
+            .try_state::<process_tree::MetricsSampler>()
+            .ok_or("Metrics sampler not found.")?;
+        sampler.sample(pid)
+    })
+    .await
+    .map_err(|e| format!("Failed to read sidecar metrics: {}", e))??;
+    Ok(metrics)
+}
+
+// Send a JSON control message (log level change, cache flush, ...) to the
+// sidecar's stdin, for things that don't warrant an HTTP round-trip. The message
+// is re-serialized compactly so it is always exactly one line, and written in
+// one go under the state lock so concurrent calls can't interleave.
+#[tauri::command]
+fn send_to_sidecar(app_handle: tauri::AppHandle, message: String) -> Result<(), ChikenError> {
+    let message: serde_json::Value = serde_json::from_str(&message)
+        .map_err(|e| format!("Sidecar messages must be JSON: {}", e))?;
+    let mut payload = serde_json::to_vec(&message).map_err(|e| e.to_string())?;
+    payload.push(b'\n');
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?;
+    let mut sidecar = state
+        .lock()
+        .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+    let child = sidecar
+        .child
+        .as_mut()
+        .ok_or(ChikenError::SidecarNotRunning)?;
+    child
+        .write(&payload)
+        .map_err(|e| format!("Failed to write to sidecar stdin: {}", e))?;
+    Ok(())
+}
+
+// How the last sidecar of this run ended, with the stderr leading up to it, for
+// the crash toast and bug reports. None until a sidecar has terminated.
+#[tauri::command]
+fn get_last_sidecar_exit(
+    app_handle: tauri::AppHandle,
+) -> Result<Option<SidecarExitReport>, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?;
+    let report = state
+        .lock()
+        .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?
+        .last_exit_report
+        .clone();
+    Ok(report)
+}
+
+// Exit code/signal of the last sidecar that terminated, None if none has yet.
+// A None code inside means the process was killed by a signal.
+#[tauri::command]
+fn get_last_exit_code(app_handle: tauri::AppHandle) -> Result<Option<SidecarExit>, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?;
+    let last_exit = state
+        .lock()
+        .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?
+        .last_exit;
+    Ok(last_exit)
+}
+
+fn normalize_backend_url(url: &str) -> Result<String, String> {
+    let url = url.trim().trim_end_matches('/');
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!(
+            "Backend URL must start with http:// or https://: {}",
+            url
+        ));
+    }
+    Ok(url.to_string())
+}
+
+// External backend to use instead of the bundled sidecar: CHIKEN_BACKEND_URL,
+// e.g. for a backend run from a conda env during development, otherwise the
+// backend.external_url setting saved by set_backend_url
+fn external_backend_url(app_handle: &tauri::AppHandle) -> Option<String> {
+    if let Some(url) = env::var("CHIKEN_BACKEND_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+    {
+        match normalize_backend_url(&url) {
+            Ok(url) => return Some(url),
+            Err(e) => tracing::warn!("Ignoring CHIKEN_BACKEND_URL: {}", e),
+        }
+    }
+    settings::get::<String>(app_handle, "backend.external_url")
+}
+
+// Connect to an externally managed backend instead of the bundled sidecar. The
+// URL is persisted so later launches skip spawning the sidecar altogether.
+// `token` is the one that backend expects, e.g. printed by a --headless
+// instance; it is kept in the secret store.
+#[tauri::command]
+async fn set_backend_url(
+    app_handle: tauri::AppHandle,
+    url: String,
+    token: Option<String>,
+) -> Result<String, ChikenError> {
+    let url = normalize_backend_url(&url)?;
+    match token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+    {
+        Some(token) => secret_store::set_secret(EXTERNAL_TOKEN_KEY, token)?,
+        None => secret_store::delete_secret(EXTERNAL_TOKEN_KEY)?,
+    }
+    settings::set(&app_handle, "backend.external_url", url.clone())?;
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+    {
+        let mut sidecar = state
+            .lock()
+            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+        sidecar.external_url = Some(url.clone());
+        sidecar.ready = false;
+    }
+
+    // The bundled sidecar is no longer needed
+    stop_sidecar_gracefully(&state, shutdown_timeout(&app_handle)).await?;
+    tracing::info!("Using external backend at {}", url);
+    app_handle.emit("backend-url-changed", url.clone()).ok();
+    tauri::async_runtime::spawn(wait_for_external_ready(
+        app_handle.clone(),
+        state,
+        url.clone(),
+    ));
+    Ok(url)
+}
+
+// Go back to spawning the bundled sidecar
+#[tauri::command]
+fn clear_backend_url(app_handle: tauri::AppHandle) -> Result<(), ChikenError> {
+    settings::delete(&app_handle, "backend.external_url")?;
+    secret_store::delete_secret(EXTERNAL_TOKEN_KEY)?;
+    if let Some(state) = app_handle.try_state::<SidecarHandle>() {
+        let mut sidecar = state
+            .lock()
+            .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+        sidecar.external_url = None;
+        sidecar.ready = false;
+    }
+    spawn_and_monitor_sidecar(app_handle)?;
+    Ok(())
+}
+
+// Extra env vars for the sidecar from the backend.env setting. A None value
+// removes the variable; changes apply from the next (re)start.
+#[tauri::command]
+fn set_backend_env(
+    app_handle: tauri::AppHandle,
+    key: String,
+    value: Option<String>,
+) -> Result<(), ChikenError> {
+    Ok(backend_env::set(&app_handle, &key, value)?)
+}
+
+#[tauri::command]
+fn list_backend_env(app_handle: tauri::AppHandle) -> BTreeMap<String, String> {
+    backend_env::list(&app_handle)
+}
+
+// Recent sidecar output for the frontend to replay on mount; live lines keep
+// arriving through sidecar-stdout-batch and sidecar-stderr afterwards
+#[tauri::command]
+fn get_sidecar_log_buffer(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<sidecar_log::LogLine>, ChikenError> {
+    let log_buffer = app_handle
+        .try_state::<sidecar_log::LogBuffer>()
+        .ok_or("Sidecar log buffer not found.")?;
+    Ok(log_buffer.snapshot())
+}
+
+// Path of the current sidecar log file so the UI can offer "open logs"
+#[tauri::command]
+fn get_log_path(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    let path = sidecar_log_path(&app_handle)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Open the log directory in the OS file manager, returning its path
+#[tauri::command]
+fn open_log_folder(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+    std::fs::create_dir_all(&log_dir)
+        .map_err(|e| format!("Failed to create {}: {}", log_dir.display(), e))?;
+    let log_dir = log_dir.to_string_lossy().to_string();
+    app_handle
+        .opener()
+        .open_path(&log_dir, None::<&str>)
+        .map_err(|e| format!("Failed to open log folder: {}", e))?;
+    Ok(log_dir)
+}
+
+// Open the data dir (see sidecar_data_dir) in the OS file manager, returning its
+// path for display
+#[tauri::command]
+fn open_data_dir(app_handle: tauri::AppHandle) -> Result<String, ChikenError> {
+    let data_dir = sidecar_data_dir(&app_handle)?.to_string_lossy().to_string();
+    app_handle
+        .opener()
+        .open_path(&data_dir, None::<&str>)
+        .map_err(|e| format!("Failed to open data folder: {}", e))?;
+    Ok(data_dir)
+}
+
+// Called by the frontend once its deep-link and deep-link-error listeners are
+// registered; links received before that, e.g. the one the app was launched
+// with, are emitted now
+#[tauri::command]
+fn deep_link_ready(app_handle: tauri::AppHandle) -> Result<(), ChikenError> {
+    let links = app_handle
+        .try_state::<deep_link::DeepLinks>()
+        .ok_or("Deep link state not found.")?;
+    links.ready(&app_handle);
+    Ok(())
+}
+
+// Change the system-wide show/hide shortcut, e.g. "CommandOrControl+Shift+Space".
+// Fails with a displayable message when the accelerator is invalid or taken.
+#[tauri::command]
+fn set_global_shortcut(
+    app_handle: tauri::AppHandle,
+    accelerator: String,
+) -> Result<(), ChikenError> {
+    Ok(hotkey::set(&app_handle, &accelerator)?)
+}
+
+#[tauri::command]
+fn clear_global_shortcut(app_handle: tauri::AppHandle) -> Result<(), ChikenError> {
+    Ok(hotkey::clear(&app_handle)?)
+}
+
+// Lets the splash window continue to the main window after a failed start
+#[tauri::command]
+fn close_splash(app_handle: tauri::AppHandle) {
+    splash::finish(&app_handle);
+}
+
+// Zip up logs, status, app info and redacted settings for a bug report, returning
+// where it was saved (None if the save dialog was cancelled)
+#[tauri::command]
+async fn export_diagnostics(app_handle: tauri::AppHandle) -> Result<Option<String>, ChikenError> {
+    let path = diagnostics::export(app_handle).await?;
+    Ok(path.map(|path| path.to_string_lossy().to_string()))
+}
+
+// Back up everything the app stores (settings, knowledge bases, logs) into one
+// zip. Secret values aren't included, only the names of the stored keys.
+#[tauri::command]
+async fn export_data(app_handle: tauri::AppHandle, dest_zip: String) -> Result<usize, ChikenError> {
+    let files = tauri::async_runtime::spawn_blocking(move || {
+        data_archive::export(&app_handle, std::path::Path::new(&dest_zip))
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))??;
+    Ok(files)
+}
+
+// Restore an export_data archive once the user confirms; the previous data is
+// kept in a backup folder. Returns false if the user cancelled.
+#[tauri::command]
+async fn import_data(app_handle: tauri::AppHandle, src_zip: String) -> Result<bool, ChikenError> {
+    Ok(data_archive::import(app_handle, PathBuf::from(src_zip)).await?)
+}
+
+// Copy text (e.g. a chat answer) to the system clipboard as-is, newlines included
+#[tauri::command]
+fn copy_to_clipboard(app_handle: tauri::AppHandle, text: String) -> Result<(), ChikenError> {
+    app_handle
+        .clipboard()
+        .write_text(text)
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+    Ok(())
+}
+
+// Show a file selected in Finder/Explorer/the Linux file manager, or open a
+// directory itself. The opener plugin passes the path as a separate argument
+// (open -R, explorer /select, or the FileManager1 DBus call with an xdg-open
+// fallback), never through a shell.
+#[tauri::command]
+fn reveal_in_file_manager(app_handle: tauri::AppHandle, path: String) -> Result<(), ChikenError> {
+    let path = PathBuf::from(path);
+    let metadata = std::fs::metadata(&path).map_err(|e| {
+        let message = format!("Cannot reveal {}: {}", path.display(), e);
+        if e.kind() == std::io::ErrorKind::NotFound {
+            ChikenError::NotFound(message)
+        } else {
+            ChikenError::Other(message)
+        }
+    })?;
+    let opener = app_handle.opener();
+    let opened = if metadata.is_dir() {
+        opener.open_path(path.to_string_lossy(), None::<&str>)
+    } else {
+        opener.reveal_item_in_dir(&path)
+    };
+    opened.map_err(|e| format!("Failed to reveal {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+// Open a cited file, folder or web page with the system default handler. Only
+// http(s) and file URLs are accepted so a crafted citation can't launch other
+// URL-scheme handlers; local paths must exist.
+#[tauri::command]
+fn open_path(app_handle: tauri::AppHandle, path_or_url: String) -> Result<(), ChikenError> {
+    let target = path_or_url.trim();
+    let path = match tauri::Url::parse(target) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            app_handle
+                .opener()
+                .open_url(url.as_str(), None::<&str>)
+                .map_err(|e| format!("Failed to open {}: {}", url, e))?;
+            return Ok(());
+        }
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|()| format!("Invalid file URL: {}", target))?,
+        // A one-letter "scheme" is a Windows drive letter, i.e. a plain path
+        Ok(url) if url.scheme().len() > 1 => {
+            return Err(format!(
+                "Refusing to open a {}: URL, only http, https and file URLs are allowed",
+                url.scheme()
+            )
+            .into());
+        }
+        _ => PathBuf::from(target),
+    };
+    if !path.exists() {
+        return Err(ChikenError::NotFound(format!(
+            "{} does not exist",
+            path.display()
+        )));
+    }
+    app_handle
+        .opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+// Open a cited document with its default app, optionally at `page`. Unlike
+// open_path it only opens files inside folders the user picked through
+// pick_directory, failing with a not_found or access_denied code the UI can explain.
+#[tauri::command]
+fn open_with_default_app(
+    app_handle: tauri::AppHandle,
+    path: String,
+    page: Option<u32>,
+) -> Result<(), ChikenError> {
+    document_roots::open(&app_handle, &path, page)
+}
+
+// Recursively find documents under `path` for bulk import. `scan_id` is chosen by
+// the caller; it tags the scan-progress events and is what cancel_scan takes.
+// `path` must lie inside a folder granted through pick_directory.
+#[tauri::command]
+async fn scan_folder_for_documents(
+    app_handle: tauri::AppHandle,
+    scan_id: String,
+    path: String,
+    extensions: Option<Vec<String>>,
+    max_depth: Option<usize>,
+) -> Result<doc_scan::ScanResult, ChikenError> {
+    document_roots::require(&app_handle, Path::new(&path))?;
+    let options = doc_scan::ScanOptions::new(path, extensions, max_depth);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let registry = app_handle
+            .try_state::<doc_scan::ScanRegistry>()
+            .ok_or("Scan registry not found.")?;
+        doc_scan::scan(&app_handle, &registry, &scan_id, options)
+    })
+    .await
+    .map_err(|e| format!("Folder scan failed: {}", e))??;
+    Ok(result)
+}
+
+// Stop a running scan; it returns what it found so far with cancelled set
+#[tauri::command]
+fn cancel_scan(app_handle: tauri::AppHandle, scan_id: String) -> Result<bool, ChikenError> {
+    let registry = app_handle
+        .try_state::<doc_scan::ScanRegistry>()
+        .ok_or("Scan registry not found.")?;
+    Ok(registry.cancel(&scan_id))
+}
+
+// SHA-256 of each file so duplicates can be skipped before ingestion. A file that
+// can't be read gets an error entry instead of failing the batch.
+#[tauri::command]
+async fn hash_files(paths: Vec<String>) -> Result<Vec<file_hash::FileHash>, ChikenError> {
+    let hashes = tauri::async_runtime::spawn_blocking(move || file_hash::hash_files(paths))
+        .await
+        .map_err(|e| format!("Hashing failed: {}", e))?;
+    Ok(hashes)
+}
+
+// Report new, changed and removed documents under `path` through
+// documents-folder-changed events until unwatch_folder or exit. Like a scan it
+// is limited to folders granted through pick_directory.
+#[tauri::command]
+fn watch_folder(
+    app_handle: tauri::AppHandle,
+    path: String,
+    extensions: Option<Vec<String>>,
+) -> Result<(), ChikenError> {
+    let watches = app_handle
+        .try_state::<folder_watch::FolderWatches>()
+        .ok_or("Folder watches not found.")?;
+    document_roots::require(&app_handle, Path::new(&path))?;
+    watches.watch(
+        app_handle.clone(),
+        PathBuf::from(path),
+        doc_scan::document_extensions(extensions),
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+fn unwatch_folder(app_handle: tauri::AppHandle, path: String) -> Result<bool, ChikenError> {
+    let watches = app_handle
+        .try_state::<folder_watch::FolderWatches>()
+        .ok_or("Folder watches not found.")?;
+    Ok(watches.unwatch(std::path::Path::new(&path)))
+}
+
+// Whether Ollama is installed and serving, so the settings page can point the
+// user at the fix before chat fails against an unreachable provider
+#[tauri::command]
+async fn ollama_status() -> ollama::OllamaStatus {
+    ollama::status().await
+}
+
+#[tauri::command]
+fn start_ollama(app_handle: tauri::AppHandle) -> Result<(), ChikenError> {
+    Ok(ollama::start(&app_handle)?)
+}
+
+// Whether Zotero is installed, running and reachable through its local API, so
+// a failing knowledge base import can say which of them is missing.
+// `data_dir` overrides where to look for the Zotero data directory.
+#[tauri::command]
+async fn zotero_status(
+    app_handle: tauri::AppHandle,
+    data_dir: Option<String>,
+) -> zotero::ZoteroStatus {
+    zotero::status(&app_handle, data_dir).await
+}
+
+// Fetch a model file in the background, resuming a <dest>.part left by an
+// earlier attempt. Returns the download id used by the other commands and events.
+#[tauri::command]
+fn start_download(
+    app_handle: tauri::AppHandle,
+    url: String,
+    dest_path: String,
+    sha256: Option<String>,
+) -> Result<String, ChikenError> {
+    Ok(downloads::start(&app_handle, url, dest_path, sha256)?)
+}
+
+#[tauri::command]
+fn cancel_download(app_handle: tauri::AppHandle, id: String) -> Result<bool, ChikenError> {
+    let downloads = app_handle
+        .try_state::<downloads::Downloads>()
+        .ok_or("Download manager not found.")?;
+    Ok(downloads.cancel(&id))
+}
+
+#[tauri::command]
+fn list_downloads(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<downloads::DownloadInfo>, ChikenError> {
+    let downloads = app_handle
+        .try_state::<downloads::Downloads>()
+        .ok_or("Download manager not found.")?;
+    Ok(downloads.list())
+}
+
+// Native folder picker for choosing where the knowledge base lives. Returns None
+// when cancelled and an error when the chosen folder can't be written to.
+#[tauri::command]
+async fn pick_directory(app_handle: tauri::AppHandle) -> Result<Option<String>, ChikenError> {
+    let dialog_handle = app_handle.clone();
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        dialog_handle.dialog().file().blocking_pick_folder()
+    })
+    .await
+    .map_err(|e| format!("Folder picker failed: {}", e))?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let dir = picked
+        .into_path()
+        .map_err(|e| format!("Unsupported folder selection: {}", e))?;
+    // Granted even if it turns out read-only, documents can still be read there
+    document_roots::grant(&app_handle, &dir);
+    check_writable_dir(&dir)?;
+    Ok(Some(dir.to_string_lossy().to_string()))
+}
+
+// Create and remove a probe file, since permission bits alone don't account for
+// ACLs, read-only mounts or sandboxing
+fn check_writable_dir(dir: &std::path::Path) -> Result<(), String> {
+    let probe = dir.join(".chiken-write-test");
+    std::fs::write(&probe, b"")
+        .and_then(|()| std::fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {}", dir.display(), e))
+}
+
+// URL of the current sidecar, whether or not it is ready yet
+fn backend_url(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?;
+    let sidecar = state
+        .lock()
+        .map_err(|_| "[tauri] Failed to acquire lock on sidecar process.")?;
+    if let Some(url) = &sidecar.external_url {
+        return Ok(url.clone());
+    }
+    let port = sidecar
+        .port
+        .ok_or("Backend port has not been assigned yet.")?;
+    Ok(format!("http://localhost:{}", port))
+}
+
+// Returned by get_backend_url and wait_for_backend: where the backend is and the
+// token to send as X-Chiken-Token. get_backend_url used to return the URL alone.
+#[derive(Clone, Serialize)]
+struct BackendConnection {
+    url: String,
+    // None for an external backend configured without a token
+    token: Option<String>,
+}
+
+fn backend_connection(app_handle: &tauri::AppHandle) -> Result<BackendConnection, String> {
+    Ok(BackendConnection {
+        url: backend_url(app_handle)?,
+        token: active_backend_token(app_handle),
+    })
+}
+
+// Resolves once the backend, bundled or external, is ready so the frontend never
+// races it
+#[tauri::command]
+async fn get_backend_url(app_handle: tauri::AppHandle) -> Result<BackendConnection, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+    wait_until_ready(&app_handle, &state, startup_timeout(&app_handle)).await?;
+    Ok(backend_connection(&app_handle)?)
+}
+
+// Resolves with the backend connection as soon as backend-ready has fired,
+// immediately if it already has, or fails after `timeout_secs` (default: the
+// startup timeout)
+#[tauri::command]
+async fn wait_for_backend(
+    app_handle: tauri::AppHandle,
+    timeout_secs: Option<u64>,
+) -> Result<BackendConnection, ChikenError> {
+    let state = app_handle
+        .try_state::<SidecarHandle>()
+        .ok_or("Sidecar process state not found.")?
+        .inner()
+        .clone();
+    let timeout = timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| startup_timeout(&app_handle));
+    wait_until_ready(&app_handle, &state, timeout).await?;
+    Ok(backend_connection(&app_handle)?)
+}
+
+static START_MINIMIZED: OnceLock<bool> = OnceLock::new();
+// Set the first time the main window is shown
+static MAIN_WINDOW_SHOWN: AtomicBool = AtomicBool::new(false);
+// Bumped on every move or resize; a scheduled save only runs if it is unchanged
+static WINDOW_STATE_CHANGES: AtomicU64 = AtomicU64::new(0);
+
+// `--minimized` (e.g. from a login item) starts the app in the tray: no splash
+// and no main window, while the sidecar still starts so indexing can run in the
+// background. The tray, the global shortcut or a second launch shows the window.
+fn start_minimized() -> bool {
+    *START_MINIMIZED.get_or_init(|| env::args().skip(1).any(|arg| arg == "--minimized"))
+}
+
+// Size, position and maximized. Visibility is left out since the main window
+// starts hidden behind the splash and is only shown once the backend is ready;
+// fullscreen so quitting from a fullscreen video doesn't reopen fullscreen; and
+// decorations, which the overlay titlebar manages.
+//
+// When started minimized, maximized is left out as well until the window is
+// first shown: restoring it on the hidden window makes some platforms show it,
+// so a previously maximized window would flash up and vanish. focus_main_window
+// applies it on first show instead, and leaving it out of saves until then
+// keeps the stored value intact for the next run.
+fn window_state_flags() -> StateFlags {
+    let flags = StateFlags::SIZE | StateFlags::POSITION | StateFlags::MAXIMIZED;
+    if start_minimized() && !MAIN_WINDOW_SHOWN.load(Ordering::Relaxed) {
+        flags - StateFlags::MAXIMIZED
+    } else {
+        flags
+    }
+}
+
+// Restore the main window's saved state. The plugin skips the main window at
+// creation so this can run after the overlay titlebar exists, which changes
+// the window's frame on Windows and would otherwise shift the restored
+// geometry. Maximized is applied last, once the window is known to be on a
+// connected monitor, so it maximizes on that monitor.
+fn restore_main_window_state(window: &tauri::WebviewWindow) {
+    let flags = window_state_flags();
+    if let Err(e) = window.restore_state(flags - StateFlags::MAXIMIZED) {
+        tracing::warn!("Failed to restore window state: {}", e);
+    }
+    ensure_on_screen(window);
+    if flags.contains(StateFlags::MAXIMIZED) {
+        window.restore_state(StateFlags::MAXIMIZED).ok();
+    }
+}
+
+// Save window state WINDOW_STATE_SAVE_DELAY after the last of a burst of moves
+// or resizes
+fn schedule_window_state_save(app_handle: &tauri::AppHandle) {
+    let change = WINDOW_STATE_CHANGES.fetch_add(1, Ordering::Relaxed) + 1;
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(WINDOW_STATE_SAVE_DELAY).await;
+        if WINDOW_STATE_CHANGES.load(Ordering::Relaxed) != change {
+            return;
+        }
+        if let Err(e) = app_handle.save_window_state(window_state_flags()) {
+            tracing::warn!("Failed to save window state: {}", e);
+        }
+    });
+}
+
+// Recover a window stuck off-screen, e.g. after unplugging a monitor: every open
+// window goes back to its configured size, centered, and that is saved over the
+// stored state. Deleting the state file alone wouldn't help, since the plugin
+// writes its in-memory copy back on exit.
+#[tauri::command]
+fn reset_window_state(app_handle: tauri::AppHandle) -> Result<(), ChikenError> {
+    for (label, window) in app_handle.webview_windows() {
+        if label == splash::LABEL {
+            continue;
+        }
+        window.set_fullscreen(false).ok();
+        window.unmaximize().ok();
+        if let Some(config) = app_handle
+            .config()
+            .app
+            .windows
+            .iter()
+            .find(|config| config.label == label)
+        {
+            window
+                .set_size(tauri::LogicalSize::new(config.width, config.height))
+                .map_err(|e| format!("Failed to resize {}: {}", label, e))?;
+        }
+        window
+            .center()
+            .map_err(|e| format!("Failed to center {}: {}", label, e))?;
+    }
+    app_handle
+        .save_window_state(window_state_flags())
+        .map_err(|e| format!("Failed to save window state: {}", e))?;
+    tracing::info!("Window state reset to defaults");
+    Ok(())
+}
+
+// The window-state plugin restores the saved position even when the monitor it
+// was on has since been unplugged. If no monitor contains the window's center,
+// move it to the middle of the primary monitor.
+fn ensure_on_screen(window: &tauri::WebviewWindow) {
+    let (Ok(position), Ok(size), Ok(monitors)) = (
+        window.outer_position(),
+        window.outer_size(),
+        window.available_monitors(),
+    ) else {
+        return;
+    };
+    let center_x = position.x + size.width as i32 / 2;
+    let center_y = position.y + size.height as i32 / 2;
+    let on_screen = monitors.iter().any(|monitor| {
+        let origin = monitor.position();
+        let extent = monitor.size();
+        (origin.x..origin.x + extent.width as i32).contains(&center_x)
+            && (origin.y..origin.y + extent.height as i32).contains(&center_y)
+    });
+    // No monitor information at all (some Wayland setups): nothing to compare against
+    if on_screen || monitors.is_empty() {
+        return;
+    }
+    let Some(primary) = window
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .or_else(|| monitors.into_iter().next())
+    else {
+        return;
+    };
+    let origin = primary.position();
+    let extent = primary.size();
+    // Pinned to the top-left corner when the window is larger than the monitor
+    let x = origin.x + (extent.width.saturating_sub(size.width) / 2) as i32;
+    let y = origin.y + (extent.height.saturating_sub(size.height) / 2) as i32;
+    tracing::info!(
+        "Window {} was restored off-screen at {:?}, moving it to ({}, {})",
+        window.label(),
+        position,
+        x,
+        y
+    );
+    if let Err(e) = window.set_position(tauri::PhysicalPosition::new(x, y)) {
+        tracing::warn!("Failed to move window {} on-screen: {}", window.label(), e);
+    }
+}
+
+// Bring the main window to the front, e.g. when a second instance is launched
+fn focus_main_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if start_minimized() && !MAIN_WINDOW_SHOWN.load(Ordering::Relaxed) {
+            window.restore_state(StateFlags::MAXIMIZED).ok();
+        }
+        MAIN_WINDOW_SHOWN.store(true, Ordering::Relaxed);
+        window.unminimize().ok();
+        window.show().ok();
+        window.set_focus().ok();
+    }
+}
+
+fn main() {
+    // Nothing is logged yet, so a bad --profile goes straight to stderr
+    if let Err(e) = profile::init() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    let mut context = tauri::generate_context!();
+    profile::apply(&mut context);
+    headless::apply(&mut context);
+    tauri::Builder::default()
+        // Must be registered first: a second launch exits here, before setup could
+        // spawn another sidecar, and hands its argv to this instance instead
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            tracing::info!("Second instance launched with args: {:?}", argv);
+            focus_main_window(app);
+            deep_link::handle(app, deep_link::from_args(&argv));
+            app.emit("second-instance-args", SecondInstanceArgs { argv, cwd })
+                .ok();
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(
+            // State is kept per window label and restored as each window is
+            // created, except for the main window (see restore_main_window_state);
+            // the splash is always centered at a fixed size instead
+            tauri_plugin_window_state::Builder::new()
+                .with_state_flags(window_state_flags())
+                .with_denylist(&[splash::LABEL])
+                .skip_initial_state("main")
+                .build(),
+        )
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_decorum::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(hotkey::on_shortcut)
+                .build(),
+        )
+        .setup(|app| {
+            let log_file_error = logging::init(app.handle());
+            if let Some(profile) = profile::current() {
+                tracing::info!("Running with profile {}", profile);
+            }
+            // Store the initial sidecar process in the app state
+            app.manage(SidecarHandle::default());
+            app.manage(sidecar_log::LogBuffer::default());
+            app.manage(sidecar_log::StderrHistory::default());
+            if let Some(e) = log_file_error {
+                sidecar_log::report_file_failure(app.handle(), &e);
+            }
+            app.manage(process_tree::MetricsSampler::default());
+            app.manage(doc_scan::ScanRegistry::default());
+            app.manage(folder_watch::FolderWatches::default());
+            app.manage(downloads::Downloads::default());
+            app.manage(deep_link::DeepLinks::default());
+            app.manage(hotkey::GlobalShortcut::default());
+            app.manage(document_roots::DocumentRoots::load(app.handle()));
+            // Clone the app handle for use elsewhere
+            let app_handle = app.handle().clone();
+            // A sidecar left behind by a crashed previous run would still hold its port
+            if let Some(pid) = sidecar_pid_file(&app_handle)
+                .and_then(|pid_file| process_tree::kill_stale_sidecar(&pid_file))
+            {
+                tracing::info!("Killed stale sidecar process {} from a previous run.", pid);
+                app_handle.emit("stale-sidecar-cleaned", pid).ok();
+            }
+            // Encrypted fallback for systems without an OS keyring
+            if secret_store::is_portable() {
+                match env::current_exe() {
+                    Ok(exe) => {
+                        let dir = exe.parent().unwrap_or(exe.as_path());
+                        tracing::info!("Portable mode, secrets are kept in {}", dir.display());
+                        secret_file::init_portable(dir);
+                    }
+                    Err(e) => tracing::warn!("Failed to get executable path: {}", e),
+                }
+            } else {
+                match app.path().app_data_dir() {
+                    Ok(data_dir) => {
+                        secret_file::init(&data_dir, app.path().app_config_dir().ok().as_deref())
+                    }
+                    Err(e) => tracing::warn!("Failed to resolve data dir: {}", e),
+                }
+            }
+            // Keyring entries used to be under the "chiken" service
+            if let Err(e) = secret_store::migrate_service_name() {
+                tracing::warn!("Failed to migrate keyring entries: {}", e);
+            }
+            // Secrets used to live in a single unnamed keyring entry
+            if let Err(e) = secret_store::migrate_legacy_secret() {
+                tracing::warn!("Failed to migrate legacy secret: {}", e);
+            }
+            let external_url = external_backend_url(&app_handle);
+            // backend.lazy_start leaves the sidecar to the first start_sidecar
+            // call, so the window shows up without waiting for the backend
+            let lazy_start = external_url.is_none()
+                && !headless::enabled()
+                && settings::get::<bool>(&app_handle, "backend.lazy_start").unwrap_or(false);
+            {
+                let state = app.state::<SidecarHandle>();
+                let mut sidecar = state.lock().unwrap();
+                sidecar.external_url = external_url.clone();
+                sidecar.deferred = lazy_start;
+            }
+            // Ctrl-C or SIGTERM, e.g. in the terminal running a dev build, quits
+            // like the tray's Quit instead of orphaning the sidecar
+            tauri::async_runtime::spawn(signals::exit_on_signal(app_handle.clone()));
+            // The main window stays hidden behind the splash until the backend is
+            // ready; without a splash there is nothing to wait behind
+            if headless::enabled() {
+                tracing::info!("Running headless, no windows will be opened.");
+                // Printed rather than logged so it stays out of the log file
+                if headless::token().is_none() {
+                    println!(
+                        "Clients must send this token as {}: {}",
+                        AUTH_TOKEN_HEADER,
+                        backend_token()
+                    );
+                }
+                #[cfg(target_os = "macos")]
+                app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+            } else if start_minimized() {
+                tracing::info!("Started with --minimized, staying in the tray.");
+            } else if lazy_start {
+                focus_main_window(&app_handle);
+            } else if let Err(e) = splash::create(app) {
+                tracing::warn!("Failed to create splash window: {}", e);
+                focus_main_window(&app_handle);
+            }
+            if let Some(url) = external_url {
+                tracing::info!("Using external backend at {}, not spawning sidecar.", url);
+                let state = app.state::<SidecarHandle>().inner().clone();
+                tauri::async_runtime::spawn(wait_for_external_ready(app_handle, state, url));
+            } else if lazy_start {
+                tracing::info!("backend.lazy_start is on, waiting for start_sidecar.");
+            } else {
+                // Spawn the Python sidecar on startup
+                tracing::info!("Creating sidecar...");
+                // Failures were already logged and emitted as sidecar-status errors
+                if spawn_and_monitor_sidecar(app_handle).is_ok() {
+                    tracing::info!("Sidecar spawned and monitoring started.");
+                }
+            }
+
+            tauri::async_runtime::spawn(watchdog::run(app.handle().clone()));
+            if headless::enabled() {
+                return Ok(());
+            }
+
+            // Windows and Linux deliver chiken:// links on the command line, macOS
+            // through the open-url event. Registering at runtime covers dev builds
+            // and AppImages, which the installers' scheme registration doesn't.
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                tracing::warn!("Failed to register the chiken:// scheme: {}", e);
+            }
+            deep_link::handle(
+                app.handle(),
+                deep_link::from_args(&env::args().collect::<Vec<_>>()),
+            );
+            let deep_link_handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let urls = event.urls().iter().map(|url| url.to_string()).collect();
+                deep_link::handle(&deep_link_handle, urls);
+            });
+
+            if let Err(e) = tray::create_tray(app) {
+                tracing::warn!("Failed to create tray icon: {}", e);
+            }
+            hotkey::restore(app.handle());
+
+            // Create a custom titlebar for main window
+            // On Windows this will hide decoration and render custom window controls
+            // On macOS it expects a hiddenTitle: true and titleBarStyle: overlay
+            let main_window = app.get_webview_window("main").unwrap();
+            main_window
+                .create_overlay_titlebar()
+                .expect("[tauri] Failed to create overlay titlebar");
+
+            restore_main_window_state(&main_window);
+
+            let zoom = get_zoom(app.handle().clone());
+            if zoom != 1.0 {
+                if let Err(e) = main_window.set_zoom(zoom) {
+                    tracing::warn!("Failed to restore zoom level: {}", e);
+                }
+            }
+
+            Ok(())
+        })
+        .on_window_event(|window, event| match event {
+            // With "minimize to tray" enabled the close button only hides the main
+            // window; the app keeps running in the tray until quit from there
+            WindowEvent::CloseRequested { api, .. } => {
+                if window.label() == "main"
+                    && settings::get::<bool>(window.app_handle(), "window.minimize_to_tray")
+                        .unwrap_or(false)
+                {
+                    api.prevent_close();
+                    window.hide().ok();
+                }
+            }
+            WindowEvent::Focused(focused) if window.label() == "main" => {
+                hotkey::set_fullscreen_shortcut(window.app_handle(), *focused);
+            }
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) if window.label() != splash::LABEL => {
+                schedule_window_state_save(window.app_handle());
+            }
+            _ => {}
+        })
+        .invoke_handler(tauri::generate_handler![
+            start_sidecar,
+            shutdown_sidecar,
+            restart_sidecar,
+            toggle_fullscreen,
+            reset_window_state,
+            set_log_level,
+            get_log_level,
+            set_zoom,
+            get_zoom,
+            get_sidecar_path,
+            get_data_dir,
+            get_current_profile,
+            get_app_info,
+            get_app_version,
+            set_secret,
+            set_validated_secret,
+            test_api_key,
+            verify_api_key,
+            get_secret,
+            delete_secret,
+            has_secret,
+            clear_all_secrets,
+            refresh_secrets_cache,
+            secret_backend_info,
+            is_portable_mode,
+            list_secrets,
+            get_device_id,
+            get_backend_url,
+            wait_for_backend,
+            get_backend_token,
+            sidecar_health,
+            sidecar_status,
+            sidecar_metrics,
+            get_last_exit_code,
+            get_last_sidecar_exit,
+            send_to_sidecar,
+            get_sidecar_log_buffer,
+            get_log_path,
+            open_log_folder,
+            open_data_dir,
+            pick_directory,
+            notify,
+            set_dock_badge,
+            close_splash,
+            deep_link_ready,
+            set_global_shortcut,
+            clear_global_shortcut,
+            export_diagnostics,
+            export_data,
+            import_data,
+            copy_to_clipboard,
+            reveal_in_file_manager,
+            open_path,
+            open_with_default_app,
+            scan_folder_for_documents,
+            cancel_scan,
+            hash_files,
+            watch_folder,
+            unwatch_folder,
+            start_download,
+            cancel_download,
+            list_downloads,
+            ollama_status,
+            start_ollama,
+            zotero_status,
+            set_backend_url,
+            clear_backend_url,
+            set_backend_env,
+            list_backend_env,
+        ])
+        .build(context)
+        .expect("Error while running tauri application")
+        .run(|app_handle, event| match event {
+            RunEvent::ExitRequested { code, api, .. } => {
+                tracing::info!("App exit requested. Attempting to shutdown sidecar...");
+                if let Err(e) = app_handle.save_window_state(window_state_flags()) {
+                    tracing::warn!("Failed to save window state: {}", e);
+                }
+                if let Some(watches) = app_handle.try_state::<folder_watch::FolderWatches>() {
+                    watches.unwatch_all();
+                }
+
+                // Hold the exit until the sidecar has had a chance to shut down
+                // gracefully; exiting again afterwards finds no sidecar and goes through
+                if let Some(state) = app_handle.try_state::<SidecarHandle>() {
+                    let state = state.inner().clone();
+                    if state.lock().unwrap().child.is_some() {
+                        api.prevent_exit();
+                        let app_handle = app_handle.clone();
+                        tauri::async_runtime::spawn(async move {
+                            match stop_sidecar_gracefully(&state, shutdown_timeout(&app_handle))
+                                .await
+                            {
+                                Ok(path) => {
+                                    tracing::info!("Sidecar terminated on app exit ({:?})", path)
+                                }
+                                Err(e) => {
+                                    tracing::error!(
+                                        "Failed to terminate sidecar on app exit: {}",
+                                        e
+                                    )
+                                }
+                            }
+                            app_handle.exit(code.unwrap_or(0));
+                        });
+                    } else {
+                        tracing::info!("No active sidecar to terminate");
+                    }
+                } else {
+                    tracing::info!("Sidecar state not found during exit");
+                }
+            }
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn only_the_first_caller_reserves_the_spawn() {
+        let mut sidecar = SidecarState {
+            deferred: true,
+            ..Default::default()
+        };
+        assert!(sidecar.reserve_spawn().unwrap());
+        assert!(sidecar.spawning);
+        assert!(!sidecar.deferred);
+        assert!(!sidecar.reserve_spawn().unwrap());
+
+        // A failed spawn releases the reservation for the next attempt
+        sidecar.spawning = false;
+        assert!(sidecar.reserve_spawn().unwrap());
+    }
+
+    #[test]
+    fn concurrent_starts_spawn_once() {
+        const CALLERS: usize = 16;
+        let state: SidecarHandle = Default::default();
+        let barrier = Arc::new(Barrier::new(CALLERS));
+        let callers: Vec<_> = (0..CALLERS)
+            .map(|_| {
+                let state = state.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    state.lock().unwrap().reserve_spawn().unwrap()
+                })
+            })
+            .collect();
+        let reserved = callers
+            .into_iter()
+            .map(|caller| caller.join().unwrap())
+            .filter(|reserved| *reserved)
+            .count();
+        assert_eq!(reserved, 1);
+    }
+
+    #[test]
+    fn external_backend_is_never_spawned() {
+        let mut sidecar = SidecarState {
+            external_url: Some("http://localhost:9000".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            sidecar.reserve_spawn(),
+            Err(ChikenError::ExternalBackend { .. })
+        ));
+        assert!(!sidecar.spawning);
+    }
+
+    #[test]
+    fn restarts_are_capped_within_the_window() {
+        let mut sidecar = SidecarState::default();
+        let start = Instant::now();
+        let attempts: Vec<_> = (0..6)
+            .map(|i| sidecar.next_restart_attempt(start + Duration::from_secs(i * 5)))
+            .collect();
+        assert_eq!(
+            attempts,
+            [Some(1), Some(2), Some(3), Some(4), Some(5), None]
+        );
+        // Still inside the window: no more attempts
+        assert_eq!(
+            sidecar.next_restart_attempt(start + Duration::from_secs(59)),
+            None
+        );
+        assert_eq!(sidecar.restart_count, MAX_SIDECAR_RESTARTS);
+    }
+
+    #[test]
+    fn a_crash_after_the_window_starts_a_new_one() {
+        let mut sidecar = SidecarState::default();
+        let start = Instant::now();
+        for i in 0..4 {
+            sidecar.next_restart_attempt(start + Duration::from_secs(i));
+        }
+        let later = start + SIDECAR_RESTART_WINDOW;
+        assert_eq!(sidecar.next_restart_attempt(later), Some(1));
+        assert_eq!(sidecar.restart_window_started, Some(later));
+
+        sidecar.reset_restarts();
+        assert_eq!(sidecar.restart_count, 0);
+        assert_eq!(sidecar.next_restart_attempt(later), Some(1));
+    }
+
+    #[test]
+    fn restart_delay_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=8)
+            .map(|attempt| sidecar_restart_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30, 30]);
+        assert_eq!(sidecar_restart_delay(0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn first_spawn_prefers_the_default_port() {
+        match TcpListener::bind(("127.0.0.1", DEFAULT_SIDECAR_PORT)) {
+            Ok(taken) => {
+                assert_ne!(choose_sidecar_port(None).unwrap(), DEFAULT_SIDECAR_PORT);
+                drop(taken);
+                assert_eq!(choose_sidecar_port(None).unwrap(), DEFAULT_SIDECAR_PORT);
+            }
+            // Held by something outside the test
+            Err(_) => assert_ne!(choose_sidecar_port(None).unwrap(), DEFAULT_SIDECAR_PORT),
+        }
+    }
+
+    #[test]
+    fn previous_port_is_reused_while_free() {
+        let port = pick_free_port().unwrap();
+        assert_eq!(choose_sidecar_port(Some(port)).unwrap(), port);
+
+        let _taken = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        assert_ne!(choose_sidecar_port(Some(port)).unwrap(), port);
+    }
+
+    #[test]
+    fn ordinary_output_is_not_a_control_line() {
+        assert!(parse_control_line("INFO: Uvicorn running on http://127.0.0.1:8009").is_none());
+        assert!(parse_control_line("").is_none());
+        assert!(parse_control_line("@@ready@@ and more").is_none());
+    }
+
+    #[test]
+    fn parses_ready_line() {
+        assert!(matches!(
+            parse_control_line(READY_LINE),
+            Some(ControlLine::Ready)
+        ));
+    }
+
+    #[test]
+    fn parses_notify_payload() {
+        let line = r#"@@notify@@{"title": "Indexed", "body": "12 documents", "kind": "success"}"#;
+        let Some(ControlLine::Notify(request)) = parse_control_line(line) else {
+            panic!("expected a notify line");
+        };
+        assert_eq!(request.title, "Indexed");
+        assert_eq!(request.body, "12 documents");
+        assert_eq!(request.kind.as_deref(), Some("success"));
+    }
+
+    #[test]
+    fn notify_body_and_kind_are_optional() {
+        let Some(ControlLine::Notify(request)) = parse_control_line(r#"@@notify@@{"title": "Hi"}"#)
+        else {
+            panic!("expected a notify line");
+        };
+        assert_eq!(request.title, "Hi");
+        assert_eq!(request.body, "");
+        assert_eq!(request.kind, None);
+    }
+
+    #[test]
+    fn malformed_notify_payload() {
+        for line in [
+            "@@notify@@",
+            "@@notify@@{not json",
+            r#"@@notify@@{"body": "no title"}"#,
+        ] {
+            assert!(
+                matches!(
+                    parse_control_line(line),
+                    Some(ControlLine::Malformed { kind: "notify", .. })
+                ),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn job_done_fills_in_defaults() {
+        let Some(ControlLine::Notify(request)) =
+            parse_control_line(r#"@@JOB_DONE {"job": "Embedding"}"#)
+        else {
+            panic!("expected a notify line");
+        };
+        assert_eq!(request.title, "ChiKen");
+        assert_eq!(request.body, "Embedding finished");
+        assert_eq!(request.kind.as_deref(), Some("success"));
+
+        assert!(matches!(
+            parse_control_line("@@JOB_DONE {"),
+            Some(ControlLine::Malformed {
+                kind: "JOB_DONE",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parses_progress_payload() {
+        let Some(ControlLine::Progress(progress)) =
+            parse_control_line(r#"@@progress@@{"stage": "loading models", "pct": 40}"#)
+        else {
+            panic!("expected a progress line");
+        };
+        assert_eq!(progress.stage, "loading models");
+        assert_eq!(progress.pct, 40);
+    }
+
+    #[test]
+    fn progress_is_clamped_to_0_to_100() {
+        for (pct, expected) in [
+            ("250", 100),
+            ("300", 100),
+            ("1e6", 100),
+            ("-1", 0),
+            ("42.6", 43),
+        ] {
+            let line = format!(r#"@@progress@@{{"stage": "x", "pct": {}}}"#, pct);
+            let Some(ControlLine::Progress(progress)) = parse_control_line(&line) else {
+                panic!("expected a progress line for {}", pct);
+            };
+            assert_eq!(progress.pct, expected, "{}", pct);
+        }
+    }
+
+    #[test]
+    fn malformed_progress_payload() {
+        for line in [
+            "@@progress@@{}",
+            r#"@@progress@@{"stage": "x"}"#,
+            r#"@@progress@@{"stage": "x", "pct": "half"}"#,
+        ] {
+            assert!(
+                matches!(
+                    parse_control_line(line),
+                    Some(ControlLine::Malformed {
+                        kind: "progress",
+                        ..
+                    })
+                ),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn parses_badge_count() {
+        assert!(matches!(
+            parse_control_line("@@badge@@3"),
+            Some(ControlLine::Badge(Some(3)))
+        ));
+        assert!(matches!(
+            parse_control_line("@@badge@@ 0 "),
+            Some(ControlLine::Badge(Some(0)))
+        ));
+        assert!(matches!(
+            parse_control_line("@@badge@@"),
+            Some(ControlLine::Badge(None))
+        ));
+        for line in ["@@badge@@three", "@@badge@@-1", "@@badge@@{\"count\": 1}"] {
+            assert!(
+                matches!(
+                    parse_control_line(line),
+                    Some(ControlLine::Malformed { kind: "badge", .. })
+                ),
+                "{}",
+                line
+            );
+        }
+    }
 }
@@ -0,0 +1,116 @@
+use tauri::{Manager, UserAttentionType};
+#[cfg(not(any(target_os = "linux", windows)))]
+use tauri_plugin_notification::NotificationExt;
+
+// Off when the user turned notifications off in settings (notifications.enabled)
+fn enabled(app_handle: &tauri::AppHandle) -> bool {
+    crate::settings::get::<bool>(app_handle, "notifications.enabled").unwrap_or(true)
+}
+
+// Show a native notification, e.g. when a long backend job finishes while the
+// window is unfocused or hidden in the tray. `kind` is "info" (default),
+// "success" or "error".
+//
+// Clicking the notification brings the main window forward, also out of the
+// tray. The notification plugin has no click callback on desktop, so on Linux
+// and Windows notify-rust (which the plugin wraps) is used directly to wait for
+// the click. On macOS its click handling needs the main run loop blocked in
+// wait_for_action, which Tauri's event loop rules out, so there the plugin
+// shows it and a click only activates the app; a window hidden in the tray
+// stays hidden until restored from the tray menu or dock.
+//
+// The main window additionally requests attention (taskbar flash / dock
+// bounce) when it isn't focused.
+pub fn show(
+    app_handle: &tauri::AppHandle,
+    title: &str,
+    body: &str,
+    kind: Option<&str>,
+) -> Result<(), String> {
+    if !enabled(app_handle) {
+        return Ok(());
+    }
+    show_native(app_handle, title, body)?;
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if !window.is_focused().unwrap_or(false) {
+            let attention = match kind {
+                Some("error") => UserAttentionType::Critical,
+                _ => UserAttentionType::Informational,
+            };
+            window.request_user_attention(Some(attention)).ok();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn show_native(app_handle: &tauri::AppHandle, title: &str, body: &str) -> Result<(), String> {
+    app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn show_native(app_handle: &tauri::AppHandle, title: &str, body: &str) -> Result<(), String> {
+    use notify_rust::{Notification, NotificationResponse};
+
+    let mut notification = Notification::new();
+    notification.summary(title).body(body).auto_icon();
+    // XDG servers only report a click on the body for a "default" action
+    #[cfg(target_os = "linux")]
+    notification.action("default", "Open");
+    // Windows only attributes toasts to the app's identifier once installed,
+    // as the plugin does; a dev build from target/ has no registered app id
+    #[cfg(windows)]
+    if !cfg!(debug_assertions) {
+        notification.app_id(&app_handle.config().identifier);
+    }
+    let app_handle = app_handle.clone();
+    // Showing and waiting both block on the notification service
+    std::thread::spawn(move || {
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::warn!("Failed to show notification: {}", e);
+                return;
+            }
+        };
+        handle
+            .wait_for_response(|response: &NotificationResponse| {
+                if matches!(
+                    response,
+                    NotificationResponse::Default | NotificationResponse::Action(_)
+                ) {
+                    crate::focus_main_window(&app_handle);
+                }
+            })
+            .ok();
+    });
+    Ok(())
+}
+
+// Show `count` on the macOS dock icon, e.g. the number of running background
+// jobs; None or 0 clears the badge. Other platforms have no dock badge and
+// ignore it.
+pub fn set_dock_badge(app_handle: &tauri::AppHandle, count: Option<u32>) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        let window = app_handle
+            .get_webview_window("main")
+            .ok_or("Main window not found.")?;
+        let label = count
+            .filter(|count| *count > 0)
+            .map(|count| count.to_string());
+        window
+            .set_badge_label(label)
+            .map_err(|e| format!("Failed to set dock badge: {}", e))?;
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app_handle, count);
+    Ok(())
+}
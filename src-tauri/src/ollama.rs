@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri_plugin_http::reqwest;
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+
+const OLLAMA_URL: &str = "http://localhost:11434";
+// Ollama answers instantly when it is up; anything slower is treated as not running
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const BINARY_NAME: &str = if cfg!(windows) {
+    "ollama.exe"
+} else {
+    "ollama"
+};
+
+#[derive(Serialize)]
+pub struct OllamaStatus {
+    pub installed: bool,
+    pub running: bool,
+    // Reported by the running server, None when it isn't reachable
+    pub version: Option<String>,
+    pub models: Vec<String>,
+    // Path of the binary that was found
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Tags {
+    #[serde(default)]
+    models: Vec<Model>,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: String,
+}
+
+// Where the official installers put the binary, for when the app was launched
+// without the user's shell PATH (e.g. from Finder or the Start menu)
+fn install_locations() -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+    if cfg!(target_os = "macos") {
+        locations.push(PathBuf::from(
+            "/Applications/Ollama.app/Contents/Resources/ollama",
+        ));
+        locations.push(PathBuf::from("/opt/homebrew/bin/ollama"));
+        locations.push(PathBuf::from("/usr/local/bin/ollama"));
+    } else if cfg!(windows) {
+        if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+            locations.push(
+                PathBuf::from(local_app_data)
+                    .join("Programs")
+                    .join("Ollama")
+                    .join(BINARY_NAME),
+            );
+        }
+    } else {
+        locations.push(PathBuf::from("/usr/local/bin/ollama"));
+        locations.push(PathBuf::from("/usr/bin/ollama"));
+        locations.push(PathBuf::from("/snap/bin/ollama"));
+    }
+    locations
+}
+
+pub fn find_binary() -> Option<PathBuf> {
+    let on_path = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(BINARY_NAME));
+    on_path
+        .chain(install_locations())
+        .find(|path| path.is_file())
+}
+
+pub async fn status() -> OllamaStatus {
+    let path = find_binary();
+    let client = reqwest::Client::new();
+    let models = match client
+        .get(format!("{}/api/tags", OLLAMA_URL))
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => Some(
+            response
+                .json::<Tags>()
+                .await
+                .map(|tags| tags.models.into_iter().map(|model| model.name).collect())
+                .unwrap_or_default(),
+        ),
+        _ => None,
+    };
+    let version = match models {
+        Some(_) => match client
+            .get(format!("{}/api/version", OLLAMA_URL))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) => response
+                .json::<Version>()
+                .await
+                .ok()
+                .map(|version| version.version),
+            Err(_) => None,
+        },
+        None => None,
+    };
+    OllamaStatus {
+        // A server answering without a binary we can see still means it's installed
+        // (e.g. running in Docker or under another user)
+        installed: path.is_some() || models.is_some(),
+        running: models.is_some(),
+        version,
+        models: models.unwrap_or_default(),
+        path: path.map(|path| path.to_string_lossy().to_string()),
+    }
+}
+
+// Launch `ollama serve` in the background. Unlike the sidecar it isn't tracked or
+// shut down by the app, since the user may keep using it outside ChiKen.
+pub fn start(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = find_binary().ok_or("Ollama is not installed")?;
+    let (mut rx, child) = app_handle
+        .shell()
+        .command(&path)
+        .args(["serve"])
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", path.display(), e))?;
+    tracing::info!("Started ollama serve (pid {})", child.pid());
+    // Keep draining its output so the pipes never fill up and block the server
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if let CommandEvent::Terminated(payload) = event {
+                tracing::info!(
+                    "ollama serve exited (code: {:?}, signal: {:?})",
+                    payload.code,
+                    payload.signal
+                );
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,248 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use sysinfo::{Pid, System};
+
+// Every process below `root` in the process tree, children before grandchildren
+fn descendants(system: &System, root: Pid) -> Vec<Pid> {
+    let mut found: Vec<Pid> = Vec::new();
+    let mut frontier = vec![root];
+    while let Some(parent) = frontier.pop() {
+        for (pid, process) in system.processes() {
+            if process.parent() == Some(parent) && !found.contains(pid) {
+                found.push(*pid);
+                frontier.push(*pid);
+            }
+        }
+    }
+    found
+}
+
+fn kill_all(system: &System, pids: Vec<Pid>) {
+    for pid in pids {
+        if let Some(process) = system.process(pid) {
+            if !process.kill() {
+                tracing::warn!("Failed to kill sidecar child process {}", pid);
+            }
+        }
+    }
+}
+
+// Kill the sidecar together with everything it spawned. pyinstaller runs the
+// backend as a child of a bootloader process, so killing the parent alone leaves
+// the Python process holding the backend port. Descendants are collected first
+// because once the parent dies they get reparented and can no longer be traced.
+pub fn kill_tree(pid: u32) {
+    let mut system = System::new();
+    system.refresh_processes();
+    let root = Pid::from_u32(pid);
+    let mut pids = vec![root];
+    pids.extend(descendants(&system, root));
+    kill_all(&system, pids);
+}
+
+// Ask a process to exit with SIGTERM
+#[cfg(unix)]
+pub fn terminate(pid: u32) {
+    let mut system = System::new();
+    system.refresh_processes();
+    if let Some(process) = system.process(Pid::from_u32(pid)) {
+        process.kill_with(sysinfo::Signal::Term);
+    }
+}
+
+// Executable of a running process, None when it can't be read, e.g. for another
+// user's process on Linux, where sysinfo reports an empty path instead
+fn process_exe(system: &System, pid: Pid) -> Option<PathBuf> {
+    system
+        .process(pid)?
+        .exe()
+        .filter(|exe| !exe.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
+
+// The pid on the first line, the executable it was running on the second
+fn parse_pid_file(contents: &str) -> Option<(u32, PathBuf)> {
+    let (pid, exe) = contents.split_once('\n')?;
+    let exe = exe.trim_end_matches(['\r', '\n']);
+    if exe.is_empty() {
+        return None;
+    }
+    Some((pid.trim().parse().ok()?, PathBuf::from(exe)))
+}
+
+// Record the sidecar's pid together with its executable, as the process reports
+// it, for kill_stale_sidecar
+pub fn write_pid_file(pid_file: &Path, pid: u32) -> Result<(), String> {
+    let mut system = System::new();
+    let sysinfo_pid = Pid::from_u32(pid);
+    system.refresh_process(sysinfo_pid);
+    let exe = process_exe(&system, sysinfo_pid).ok_or_else(|| {
+        format!(
+            "Not recording sidecar process {}: its executable can't be read",
+            pid
+        )
+    })?;
+    if let Some(dir) = pid_file.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(pid_file, format!("{}\n{}", pid, exe.display()))
+        .map_err(|e| format!("Failed to write {}: {}", pid_file.display(), e))
+}
+
+// Kill the sidecar recorded in `pid_file` by a previous run that crashed or was
+// force-quit, returning its pid. The pid is only trusted while it still runs the
+// recorded executable, so an unrelated process that reused it is left alone,
+// whatever it is called. A file without an executable, as older versions wrote
+// it, is not trusted either.
+pub fn kill_stale_sidecar(pid_file: &Path) -> Option<u32> {
+    let contents = fs::read_to_string(pid_file).ok()?;
+    fs::remove_file(pid_file).ok();
+    let (pid, recorded_exe) = parse_pid_file(&contents)?;
+
+    let mut system = System::new();
+    system.refresh_processes();
+    let process = system.process(Pid::from_u32(pid))?;
+    if process_exe(&system, process.pid()).as_deref() != Some(recorded_exe.as_path()) {
+        return None;
+    }
+    let mut pids = vec![process.pid()];
+    pids.extend(descendants(&system, process.pid()));
+    kill_all(&system, pids);
+    Some(pid)
+}
+
+#[derive(Serialize)]
+pub struct ProcessMetrics {
+    pub pid: u32,
+    // Resident memory of the whole tree, in bytes
+    pub rss_bytes: u64,
+    // Summed over the tree, where 100 is one fully busy core
+    pub cpu_percent: f32,
+    // Number of processes measured, the sidecar included
+    pub processes: usize,
+}
+
+// Keeps one System between calls: CPU usage is measured between two refreshes,
+// so the previous sample has to survive until the next poll. Only the sidecar's
+// own processes are refreshed; the full process list is only read to find the
+// pyinstaller child doing the actual work.
+#[derive(Default)]
+pub struct MetricsSampler(Mutex<Sampler>);
+
+#[derive(Default)]
+struct Sampler {
+    system: System,
+    root: Option<Pid>,
+    tree: Vec<Pid>,
+}
+
+impl MetricsSampler {
+    // Blocks for sysinfo::MINIMUM_CPU_UPDATE_INTERVAL on the first call for a
+    // new pid to get a meaningful CPU reading; run it through spawn_blocking
+    pub fn sample(&self, pid: u32) -> Result<ProcessMetrics, String> {
+        let mut sampler = self.0.lock().unwrap();
+        let root = Pid::from_u32(pid);
+        let new_root = sampler.root != Some(root);
+        if new_root {
+            sampler.system = System::new();
+            sampler.tree.clear();
+            sampler.root = Some(root);
+        }
+        // Looked up again while no child was found, since shortly after spawn the
+        // bootloader may not have started it yet
+        if sampler.tree.len() <= 1 {
+            sampler.system.refresh_processes();
+            let mut tree = vec![root];
+            tree.extend(descendants(&sampler.system, root));
+            sampler.tree = tree;
+        }
+        if new_root {
+            std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        }
+        let Sampler { system, tree, .. } = &mut *sampler;
+        tree.retain(|pid| system.refresh_process(*pid));
+        if !tree.contains(&root) {
+            sampler.root = None;
+            return Err(format!("Sidecar process {} is not running", pid));
+        }
+        let processes: Vec<&sysinfo::Process> =
+            tree.iter().filter_map(|pid| system.process(*pid)).collect();
+        Ok(ProcessMetrics {
+            pid,
+            rss_bytes: processes.iter().map(|process| process.memory()).sum(),
+            cpu_percent: processes.iter().map(|process| process.cpu_usage()).sum(),
+            processes: processes.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A pid file path under the system temp dir, its directory removed by the test
+    fn pid_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chiken-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir.join("sidecar.pid")
+    }
+
+    #[test]
+    fn pid_file_needs_pid_and_executable() {
+        assert_eq!(
+            parse_pid_file("123\n/opt/chiken/chicken-core\n"),
+            Some((123, PathBuf::from("/opt/chiken/chicken-core")))
+        );
+        assert_eq!(
+            parse_pid_file("7\r\nC:\\Program Files\\ChiKen\\chicken-core.exe"),
+            Some((
+                7,
+                PathBuf::from("C:\\Program Files\\ChiKen\\chicken-core.exe")
+            ))
+        );
+        assert_eq!(parse_pid_file("123"), None);
+        assert_eq!(parse_pid_file("123\n"), None);
+        assert_eq!(parse_pid_file("abc\n/bin/sh"), None);
+    }
+
+    #[test]
+    fn pid_reused_by_another_executable_is_left_alone() {
+        let path = pid_file("pid-reused");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        // This test process stands in for whatever got the sidecar's old pid
+        fs::write(
+            &path,
+            format!("{}\n/opt/chiken/chicken-core", std::process::id()),
+        )
+        .unwrap();
+        assert_eq!(kill_stale_sidecar(&path), None);
+        assert!(!path.exists());
+
+        // Same for a file that only has the pid
+        fs::write(&path, std::process::id().to_string()).unwrap();
+        assert_eq!(kill_stale_sidecar(&path), None);
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_sidecar_running_the_recorded_executable_is_killed() {
+        let path = pid_file("pid-stale");
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        write_pid_file(&path, child.id()).unwrap();
+        let (pid, exe) = parse_pid_file(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(pid, child.id());
+        assert!(exe.is_absolute(), "{}", exe.display());
+
+        assert_eq!(kill_stale_sidecar(&path), Some(child.id()));
+        assert!(!child.wait().unwrap().success());
+        assert!(!path.exists());
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::sync::OnceLock;
+
+// Longest accepted profile name; it ends up in directory and keyring names
+const MAX_NAME_LEN: usize = 32;
+
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// `--profile <name>` or `--profile=<name>`, the last one winning
+fn from_args(mut args: impl Iterator<Item = String>) -> Result<Option<String>, String> {
+    let mut profile = None;
+    while let Some(arg) = args.next() {
+        let name = if arg == "--profile" {
+            args.next()
+                .ok_or("--profile needs a name, e.g. --profile work")?
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            name.to_string()
+        } else {
+            continue;
+        };
+        if !is_valid_name(&name) {
+            return Err(format!(
+                "Invalid profile name '{}': use up to {} letters, digits, '-' or '_'",
+                name, MAX_NAME_LEN
+            ));
+        }
+        // Lowercased since the dirs derived from it may be case-insensitive
+        profile = Some(name.to_ascii_lowercase());
+    }
+    Ok(profile)
+}
+
+// Parse the command line once, before the Tauri context is built
+pub fn init() -> Result<Option<&'static str>, String> {
+    let profile = from_args(env::args().skip(1))?;
+    Ok(PROFILE.get_or_init(|| profile).as_deref())
+}
+
+// The active profile, None for the default one
+pub fn current() -> Option<&'static str> {
+    PROFILE.get().and_then(|profile| profile.as_deref())
+}
+
+// Suffix the bundle identifier with the profile. The app data, config and log
+// dirs (and with them the settings store, window state and sidecar data) are
+// all derived from it, and so is the single-instance lock, which lets two
+// profiles run side by side.
+pub fn apply<R: tauri::Runtime>(context: &mut tauri::Context<R>) {
+    if let Some(profile) = current() {
+        let config = context.config_mut();
+        config.identifier = format!("{}.{}", config.identifier, profile);
+    }
+}
@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+
+pub const REDACTED: &str = "***REDACTED***";
+// Shorter values are too likely to match ordinary words in the backend's output
+const MIN_SECRET_LEN: usize = 8;
+// Characters after "sk-" needed before a token is treated as an API key
+const MIN_KEY_BODY_LEN: usize = 20;
+
+// Scrubs stored secret values and anything shaped like an OpenAI-style key
+// (sk-...) from sidecar output before it reaches the frontend or the log file.
+// Matching is per line only; a secret split across two lines is not caught.
+pub struct Redactor {
+    // Longest first, so a secret containing another is replaced whole
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+            .collect();
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets.dedup();
+        Self { secrets }
+    }
+
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        for secret in &self.secrets {
+            if line.contains(secret.as_str()) {
+                line = Cow::Owned(line.replace(secret.as_str(), REDACTED));
+            }
+        }
+        if line.contains("sk-") {
+            line = Cow::Owned(redact_api_keys(&line));
+        }
+        line
+    }
+}
+
+// Replace sk-<body> tokens whose body is at least MIN_KEY_BODY_LEN characters.
+// Dashes and underscores count towards the body so project keys (sk-proj-...)
+// are caught too.
+fn redact_api_keys(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("sk-") {
+        let body = &rest[start + 3..];
+        let body_len = body
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(body.len());
+        out.push_str(&rest[..start]);
+        if body_len >= MIN_KEY_BODY_LEN {
+            out.push_str(REDACTED);
+        } else {
+            out.push_str(&rest[start..start + 3 + body_len]);
+        }
+        rest = &body[body_len..];
+    }
+    out.push_str(rest);
+    out
+}
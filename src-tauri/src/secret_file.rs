@@ -0,0 +1,222 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// Encrypted fallback used when no OS keyring is reachable (e.g. a Linux session
+// without a secret service). All secrets live in one file: a 12-byte nonce
+// followed by the ChaCha20-Poly1305 encrypted JSON map of key -> value.
+//
+// The key is derived from machine and user identifiers, so the file is useless
+// when copied elsewhere but offers no protection against someone who can run
+// code as this user. secret_backend_info lets the UI point that out. The device
+// ID can't serve as the key: without a keyring it is stored in this very file.
+//
+// In portable mode the file sits next to the executable and has to keep working
+// on whichever machine the app is run from, so the key is a random one kept in
+// secrets.key beside it instead. That only guards against the file being
+// copied without its key.
+pub const FILE_NAME: &str = "secrets.enc";
+const KEY_FILE_NAME: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+static PORTABLE_KEY: OnceLock<Vec<u8>> = OnceLock::new();
+// Held across the read-modify-write in set and delete. Without it two commands
+// storing different keys at once both load the old map and the second save
+// drops the first key.
+static LOCK: Mutex<()> = Mutex::new(());
+
+// Called once from setup. The file used to live in the app config dir; move it
+// into the data dir next to the rest of the app's state.
+pub fn init(data_dir: &Path, legacy_dir: Option<&Path>) {
+    let path = data_dir.join(FILE_NAME);
+    if let Some(legacy) = legacy_dir.map(|dir| dir.join(FILE_NAME)) {
+        if legacy != path && legacy.exists() && !path.exists() {
+            let moved = fs::create_dir_all(data_dir).and_then(|()| fs::rename(&legacy, &path));
+            if let Err(e) = moved {
+                tracing::warn!("Failed to move {}: {}", legacy.display(), e);
+            }
+        }
+    }
+    PATH.set(path).ok();
+}
+
+// Portable mode counterpart of init, keeping the file and its key in `dir`.
+// If the key can't be read or created (e.g. a read-only stick) the store stays
+// uninitialized and storing a secret fails.
+pub fn init_portable(dir: &Path) {
+    match portable_key(&dir.join(KEY_FILE_NAME)) {
+        Ok(key) => {
+            PORTABLE_KEY.set(key).ok();
+            PATH.set(dir.join(FILE_NAME)).ok();
+        }
+        Err(e) => tracing::warn!("Portable secret storage is unavailable: {}", e),
+    }
+}
+
+fn portable_key(path: &Path) -> Result<Vec<u8>, String> {
+    match fs::read(path) {
+        Ok(key) if key.len() == KEY_LEN => return Ok(key),
+        Ok(_) => return Err(format!("{} is corrupted", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+    fs::write(path, &key).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).ok();
+    }
+    Ok(key)
+}
+
+pub fn path() -> Option<&'static PathBuf> {
+    PATH.get()
+}
+
+fn require_path() -> Result<&'static PathBuf, String> {
+    path().ok_or_else(|| "Secret file storage is not initialized".to_string())
+}
+
+fn machine_id() -> String {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn cipher() -> ChaCha20Poly1305 {
+    if let Some(key) = PORTABLE_KEY.get() {
+        return ChaCha20Poly1305::new(Key::from_slice(key));
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(b"chiken-secret-file-v1");
+    hasher.update(machine_id().as_bytes());
+    hasher.update(whoami::fallible::hostname().unwrap_or_default().as_bytes());
+    hasher.update(whoami::username().as_bytes());
+    ChaCha20Poly1305::new(Key::from_slice(&hasher.finalize()))
+}
+
+fn load() -> Result<BTreeMap<String, String>, String> {
+    let path = require_path()?;
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    if data.len() < NONCE_LEN {
+        return Err(format!("{} is corrupted", path.display()));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| format!("Failed to decrypt {}", path.display()))?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse secret file: {}", e))
+}
+
+fn save(secrets: &BTreeMap<String, String>) -> Result<(), String> {
+    let path = require_path()?;
+    if secrets.is_empty() {
+        return match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+        };
+    }
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt secrets".to_string())?;
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    // Write to a temporary file first so a crash never leaves a truncated store
+    let tmp = path.with_extension("enc.tmp");
+    fs::write(&tmp, data).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600)).ok();
+    }
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn get(key: &str) -> Result<Option<String>, String> {
+    if path().is_none() {
+        return Ok(None);
+    }
+    Ok(load()?.remove(key))
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    let _guard = LOCK.lock().unwrap();
+    let mut secrets = load()?;
+    secrets.insert(key.to_string(), value.to_string());
+    save(&secrets)
+}
+
+pub fn delete(key: &str) -> Result<(), String> {
+    if path().is_none() {
+        return Ok(());
+    }
+    let _guard = LOCK.lock().unwrap();
+    let mut secrets = load()?;
+    if secrets.remove(key).is_some() {
+        save(&secrets)?;
+    }
+    Ok(())
+}
+
+pub fn keys() -> Result<Vec<String>, String> {
+    if path().is_none() {
+        return Ok(Vec::new());
+    }
+    Ok(load()?.into_keys().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn concurrent_writes_keep_every_key() {
+        let dir = std::env::temp_dir().join(format!("chiken-secret-file-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        init(&dir, None);
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| thread::spawn(move || set(&format!("key-{}", i), &format!("value-{}", i))))
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+        let expected: Vec<_> = (0..8).map(|i| format!("key-{}", i)).collect();
+        let mut stored = keys().unwrap();
+        stored.sort();
+        assert_eq!(stored, expected);
+
+        let removers: Vec<_> = (0..4)
+            .map(|i| thread::spawn(move || delete(&format!("key-{}", i))))
+            .collect();
+        for remover in removers {
+            remover.join().unwrap().unwrap();
+        }
+        assert_eq!(keys().unwrap(), expected[4..]);
+        assert_eq!(get("key-5").unwrap().as_deref(), Some("value-5"));
+        assert!(!dir.join("secrets.enc.tmp").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
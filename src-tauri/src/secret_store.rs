@@ -1,24 +1,703 @@
+use crate::error::ChikenError;
+use crate::secret_file;
 use keyring::Entry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
 use whoami;
 
-const SERVICE_NAME: &str = "chiken"; // service name as requested
+// Keychain service, shown as the item name in the macOS keychain prompt and in
+// Keychain Access; the app's bundle identifier so it is recognizably ChiKen's.
+// No separate label is set. keyring 2 has no way to, and keyring 3 only adds
+// label/comment attributes for the Linux secret service and the Windows
+// credential manager, not for the macOS keychain where the prompt appears, so
+// upgrading wouldn't change what the prompt shows.
+const SERVICE_NAME: &str = "com.github.yuanjua.chiken";
+// Service name used before, migrated by migrate_service_name
+const OLD_SERVICE_NAME: &str = "chiken";
 
-pub fn set_secret(value: &str) -> Result<(), String> {
-    let username = whoami::username();
-    let entry = Entry::new(SERVICE_NAME, &username)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    entry
-        .set_password(value)
-        .map_err(|e| format!("Failed to set secret: {}", e))
+// Key the old single, unnamed secret is migrated to
+pub const DEFAULT_KEY: &str = "default";
+// Reserved entry holding a JSON array of every stored key, since keyring
+// offers no way to enumerate entries
+const INDEX_KEY: &str = "__index__";
+// Reserved entry holding the anonymous per-install ID, see device_id
+const DEVICE_ID_KEY: &str = "__device_id__";
+
+static DEVICE_ID: OnceLock<String> = OnceLock::new();
+static PORTABLE: OnceLock<bool> = OnceLock::new();
+static SERVICE: OnceLock<String> = OnceLock::new();
+// Values read through get_secret, None for keys known to be unset. On macOS every
+// keychain read may prompt for Touch ID or a password, and the frontend reads on
+// every provider switch. Lives only in memory: nothing here is ever written out
+// or put in diagnostics, and it is gone when the process exits.
+static CACHE: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+
+// Where secrets are currently being stored, reported by secret_backend_info
+#[derive(Serialize)]
+pub struct BackendInfo {
+    // "keyring" or "file"
+    pub backend: &'static str,
+    // Set whenever the encrypted fallback file exists, even if the keyring is
+    // reachable again, since it may still hold secrets from an earlier session
+    pub file_path: Option<String>,
+}
+
+// CHIKEN_PORTABLE=1, for running from a USB stick or a machine where the OS
+// keyring is off-limits: the keyring is never touched and every secret goes to
+// the encrypted file next to the executable (see secret_file::init_portable)
+pub fn is_portable() -> bool {
+    *PORTABLE.get_or_init(|| {
+        std::env::var("CHIKEN_PORTABLE").is_ok_and(|value| value == "1" || value == "true")
+    })
+}
+
+// In portable mode every lookup fails as if there were no keyring, which sends
+// all operations down the same encrypted-file path
+fn portable_guard() -> keyring::Result<()> {
+    if is_portable() {
+        return Err(keyring::Error::NoStorageAccess(
+            "portable mode (CHIKEN_PORTABLE) is on".into(),
+        ));
+    }
+    Ok(())
+}
+
+// With --profile the service gets the profile as a suffix, like the bundle
+// identifier, so each profile has its own keys
+fn service_name() -> &'static str {
+    SERVICE.get_or_init(|| match crate::profile::current() {
+        Some(profile) => format!("{}.{}", SERVICE_NAME, profile),
+        None => SERVICE_NAME.to_string(),
+    })
+}
+
+fn old_service_name() -> String {
+    match crate::profile::current() {
+        Some(profile) => format!("{}:{}", OLD_SERVICE_NAME, profile),
+        None => OLD_SERVICE_NAME.to_string(),
+    }
+}
+
+fn account(key: &str) -> String {
+    format!("chiken:{}", key)
+}
+
+// Before named secrets there was a single entry keyed by the OS username
+fn legacy_account() -> String {
+    whoami::username()
+}
+
+// The keyring operations this module needs, by service and account. Everything
+// below goes through it, so tests can stand in a keyring that fails the way a
+// machine without a secret service does.
+trait Keyring {
+    fn get(&self, service: &str, account: &str) -> keyring::Result<String>;
+    fn set(&self, service: &str, account: &str, value: &str) -> keyring::Result<()>;
+    fn delete(&self, service: &str, account: &str) -> keyring::Result<()>;
+}
+
+// The platform keyring, or none at all in portable mode
+struct OsKeyring;
+
+impl OsKeyring {
+    fn entry(service: &str, account: &str) -> keyring::Result<Entry> {
+        portable_guard()?;
+        Entry::new(service, account)
+    }
+}
+
+impl Keyring for OsKeyring {
+    fn get(&self, service: &str, account: &str) -> keyring::Result<String> {
+        Self::entry(service, account)?.get_password()
+    }
+
+    fn set(&self, service: &str, account: &str, value: &str) -> keyring::Result<()> {
+        Self::entry(service, account)?.set_password(value)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> keyring::Result<()> {
+        Self::entry(service, account)?.delete_password()
+    }
+}
+
+// The user clicked "Deny" on the macOS keychain prompt. keyring reports that as
+// a generic platform failure, so the Security framework code is checked.
+#[cfg(target_os = "macos")]
+fn access_denied(e: &keyring::Error) -> bool {
+    // errSecUserCanceled, errSecAuthFailed
+    const DENIED: [i32; 2] = [-128, -25293];
+    match e {
+        keyring::Error::PlatformFailure(inner) => inner
+            .downcast_ref::<security_framework::base::Error>()
+            .is_some_and(|inner| DENIED.contains(&inner.code())),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn access_denied(_e: &keyring::Error) -> bool {
+    false
+}
+
+// Errors meaning there is no usable keyring at all (no secret service on DBus,
+// locked-down keychain, ...), as opposed to a missing entry. These switch over to
+// the encrypted file instead of failing. A denied prompt is not one of them:
+// falling back would hide that the keychain is there and the user can allow it.
+fn keyring_unavailable(e: &keyring::Error) -> bool {
+    matches!(
+        e,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    ) && !access_denied(e)
+}
+
+// A keyring that is there but failed the operation
+fn keyring_error(context: &str, e: keyring::Error) -> ChikenError {
+    if access_denied(&e) {
+        return ChikenError::KeyringAccessDenied(format!(
+            "{}: access to the keychain was denied. Allow ChiKen in Keychain Access and try again.",
+            context
+        ));
+    }
+    ChikenError::Keyring(format!("{}: {}", context, e))
+}
+
+// Move entries from the old "chiken" service to SERVICE_NAME, returning how
+// many moved. The old index says which keys exist, since the keyring can't be
+// enumerated, and is merged into the new one. An entry already present under the
+// new service wins.
+pub fn migrate_service_name() -> Result<usize, ChikenError> {
+    if is_portable() {
+        return Ok(0);
+    }
+    let keyring = &OsKeyring;
+    let old_service = old_service_name();
+    let old_index: Vec<String> = match keyring.get(&old_service, &account(INDEX_KEY)) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse old secret index: {}", e))?,
+        Err(keyring::Error::NoEntry) => Vec::new(),
+        Err(e) if keyring_unavailable(&e) => return Ok(0),
+        Err(e) => return Err(keyring_error("Failed to read old secret index", e)),
+    };
+    let mut moved = 0;
+    // The legacy username entry moves too; the backend reads it under SERVICE_NAME
+    let accounts = old_index
+        .iter()
+        .map(|key| account(key))
+        .chain([account(DEVICE_ID_KEY), legacy_account()]);
+    for account in accounts {
+        let value = match keyring.get(&old_service, &account) {
+            Ok(value) => value,
+            Err(keyring::Error::NoEntry) => continue,
+            Err(e) => return Err(keyring_error("Failed to read old entry", e)),
+        };
+        match keyring.get(service_name(), &account) {
+            Ok(_) => {}
+            Err(keyring::Error::NoEntry) => {
+                keyring
+                    .set(service_name(), &account, &value)
+                    .map_err(|e| keyring_error("Failed to migrate secret", e))?;
+                moved += 1;
+            }
+            Err(e) => return Err(keyring_error("Failed to check migrated secret", e)),
+        }
+        keyring
+            .delete(&old_service, &account)
+            .map_err(|e| keyring_error("Failed to remove migrated secret", e))?;
+    }
+    if !old_index.is_empty() {
+        let mut index = read_index(keyring)?;
+        index.extend(old_index);
+        index.sort();
+        index.dedup();
+        write_index(keyring, &index)?;
+        keyring
+            .delete(&old_service, &account(INDEX_KEY))
+            .map_err(|e| keyring_error("Failed to remove old secret index", e))?;
+    }
+    if moved > 0 {
+        tracing::info!(
+            "Moved {} keyring entries from service '{}' to '{}'",
+            moved,
+            old_service,
+            service_name()
+        );
+    }
+    Ok(moved)
+}
+
+// Move the legacy secret under DEFAULT_KEY, returning it if there was one.
+// Runs at startup so existing installs see their key under the new name (and in
+// list_secret_keys) right away; get_secret(DEFAULT_KEY) falls back to it as well.
+// The backend's keychain_loader.py uses DEFAULT_KEY too, so nothing reads the
+// legacy entry once it is gone.
+pub fn migrate_legacy_secret() -> Result<Option<String>, ChikenError> {
+    migrate_legacy(&OsKeyring)
+}
+
+fn migrate_legacy(keyring: &dyn Keyring) -> Result<Option<String>, ChikenError> {
+    let value = match keyring.get(service_name(), &legacy_account()) {
+        Ok(val) => val,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) if keyring_unavailable(&e) => return Ok(None),
+        Err(e) => return Err(keyring_error("Failed to get secret", e)),
+    };
+    let stored = store_secret(keyring, DEFAULT_KEY, &value);
+    cache().remove(DEFAULT_KEY);
+    stored?;
+    keyring
+        .delete(service_name(), &legacy_account())
+        .map_err(|e| keyring_error("Failed to remove migrated secret", e))?;
+    tracing::info!("Migrated legacy secret to key '{}'", DEFAULT_KEY);
+    Ok(Some(value))
+}
+
+fn read_index(keyring: &dyn Keyring) -> Result<Vec<String>, ChikenError> {
+    match keyring.get(service_name(), &account(INDEX_KEY)) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse secret index: {}", e).into()),
+        Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+        Err(e) if keyring_unavailable(&e) => Ok(Vec::new()),
+        Err(e) => Err(keyring_error("Failed to read secret index", e)),
+    }
+}
+
+fn write_index(keyring: &dyn Keyring, keys: &[String]) -> Result<(), ChikenError> {
+    let json = serde_json::to_string(keys).map_err(|e| e.to_string())?;
+    keyring
+        .set(service_name(), &account(INDEX_KEY), &json)
+        .map_err(|e| keyring_error("Failed to write secret index", e))
+}
+
+fn is_reserved(key: &str) -> bool {
+    key == INDEX_KEY || key == DEVICE_ID_KEY
+}
+
+pub fn list_secret_keys() -> Result<Vec<String>, ChikenError> {
+    let mut keys = read_index(&OsKeyring)?;
+    keys.extend(
+        secret_file::keys()?
+            .into_iter()
+            .filter(|key| !is_reserved(key)),
+    );
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+fn cache() -> MutexGuard<'static, HashMap<String, Option<String>>> {
+    CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Forget every cached value, e.g. after the keychain was edited outside the app
+pub fn refresh_cache() {
+    cache().clear();
+}
+
+// The cache entry is dropped after the write, so a read racing with it can't
+// leave the old value cached
+pub fn set_secret(key: &str, value: &str) -> Result<(), ChikenError> {
+    let result = store_secret(&OsKeyring, key, value);
+    cache().remove(key);
+    result
+}
+
+fn store_secret(keyring: &dyn Keyring, key: &str, value: &str) -> Result<(), ChikenError> {
+    if is_reserved(key) {
+        return Err(format!("'{}' is a reserved secret key", key).into());
+    }
+    match keyring.set(service_name(), &account(key), value) {
+        Ok(()) => {}
+        Err(e) if keyring_unavailable(&e) => {
+            tracing::info!(
+                "No OS keyring available ({}), storing secret '{}' in {}",
+                e,
+                key,
+                secret_file::FILE_NAME
+            );
+            return secret_file::set(key, value).map_err(ChikenError::KeyringUnavailable);
+        }
+        Err(e) => return Err(keyring_error("Failed to set secret", e)),
+    }
+
+    let mut keys = read_index(keyring)?;
+    if !keys.iter().any(|k| k == key) {
+        keys.push(key.to_string());
+        keys.sort();
+        write_index(keyring, &keys)?;
+    }
+    // Drop any copy left in the fallback file by an earlier keyring-less session
+    Ok(secret_file::delete(key)?)
+}
+
+pub fn get_secret(key: &str) -> Result<Option<String>, ChikenError> {
+    if let Some(value) = cache().get(key) {
+        return Ok(value.clone());
+    }
+    let value = read_secret(&OsKeyring, key)?;
+    cache().insert(key.to_string(), value.clone());
+    Ok(value)
 }
 
-pub fn get_secret() -> Result<Option<String>, String> {
-    let username = whoami::username();
-    let entry = Entry::new(SERVICE_NAME, &username)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    match entry.get_password() {
+fn read_secret(keyring: &dyn Keyring, key: &str) -> Result<Option<String>, ChikenError> {
+    match keyring.get(service_name(), &account(key)) {
         Ok(val) => Ok(Some(val)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(format!("Failed to get secret: {}", e)),
+        Err(keyring::Error::NoEntry) if key == DEFAULT_KEY => match migrate_legacy(keyring)? {
+            Some(val) => Ok(Some(val)),
+            None => Ok(secret_file::get(key)?),
+        },
+        Err(keyring::Error::NoEntry) => Ok(secret_file::get(key)?),
+        Err(e) if keyring_unavailable(&e) => {
+            secret_file::get(key).map_err(ChikenError::KeyringUnavailable)
+        }
+        Err(e) => Err(keyring_error("Failed to get secret", e)),
+    }
+}
+
+// Whether a secret is stored under `key`, without the value ever leaving this
+// module. Cheap enough to call on every settings render.
+pub fn has_secret(key: &str) -> Result<bool, ChikenError> {
+    if let Some(value) = cache().get(key) {
+        return Ok(value.is_some());
+    }
+    let keyring = &OsKeyring;
+    match keyring.get(service_name(), &account(key)) {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) if key == DEFAULT_KEY && has_legacy_secret(keyring)? => {
+            Ok(true)
+        }
+        Err(keyring::Error::NoEntry) => Ok(secret_file::get(key)?.is_some()),
+        Err(e) if keyring_unavailable(&e) => secret_file::get(key)
+            .map(|value| value.is_some())
+            .map_err(ChikenError::KeyringUnavailable),
+        Err(e) => Err(keyring_error("Failed to check secret", e)),
+    }
+}
+
+fn has_legacy_secret(keyring: &dyn Keyring) -> Result<bool, ChikenError> {
+    match keyring.get(service_name(), &legacy_account()) {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) if keyring_unavailable(&e) => Ok(false),
+        Err(e) => Err(keyring_error("Failed to check secret", e)),
+    }
+}
+
+pub fn delete_secret(key: &str) -> Result<(), ChikenError> {
+    let result = remove_secret(&OsKeyring, key);
+    cache().remove(key);
+    result
+}
+
+fn remove_secret(keyring: &dyn Keyring, key: &str) -> Result<(), ChikenError> {
+    match keyring.delete(service_name(), &account(key)) {
+        Ok(()) | Err(keyring::Error::NoEntry) => {
+            let mut keys = read_index(keyring)?;
+            if let Some(pos) = keys.iter().position(|k| k == key) {
+                keys.remove(pos);
+                write_index(keyring, &keys)?;
+            }
+        }
+        Err(e) if keyring_unavailable(&e) => {}
+        Err(e) => return Err(keyring_error("Failed to delete secret", e)),
+    }
+    Ok(secret_file::delete(key)?)
+}
+
+// Result of clear_all_secrets. One failed delete doesn't stop the others, so a
+// reset removes as much as it can and the UI can list what is left.
+#[derive(Default, Serialize)]
+pub struct ClearReport {
+    pub removed: usize,
+    pub failed: Vec<ClearFailure>,
+}
+
+#[derive(Serialize)]
+pub struct ClearFailure {
+    // The secret key, or the index or secrets file that couldn't be read or removed
+    pub key: String,
+    pub error: String,
+}
+
+impl ClearReport {
+    fn fail(&mut self, key: &str, error: impl ToString) {
+        self.failed.push(ClearFailure {
+            key: key.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+// Remove every secret in the index or the fallback file, then the index itself.
+// Safe to call with nothing stored, which removes nothing.
+pub fn clear_all_secrets() -> ClearReport {
+    refresh_cache();
+    let mut report = ClearReport::default();
+    let mut keys = read_index(&OsKeyring).unwrap_or_else(|e| {
+        report.fail(INDEX_KEY, e);
+        Vec::new()
+    });
+    match secret_file::keys() {
+        Ok(file_keys) => keys.extend(file_keys.into_iter().filter(|key| !is_reserved(key))),
+        Err(e) => report.fail(secret_file::FILE_NAME, e),
+    }
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        match delete_secret(&key) {
+            Ok(()) => report.removed += 1,
+            Err(e) => report.fail(&key, e),
+        }
+    }
+    match OsKeyring.delete(service_name(), &account(INDEX_KEY)) {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) if keyring_unavailable(&e) => {}
+        Err(e) => report.fail(INDEX_KEY, keyring_error("Failed to delete secret index", e)),
+    }
+    if !report.failed.is_empty() {
+        tracing::warn!(
+            "Cleared {} secret(s), {} could not be removed",
+            report.removed,
+            report.failed.len()
+        );
+    }
+    report
+}
+
+// Stable anonymous identifier for this install, generated on first use and kept
+// next to the secrets (outside the index, so it is never listed or cleared)
+pub fn device_id() -> Result<String, ChikenError> {
+    if let Some(id) = DEVICE_ID.get() {
+        return Ok(id.clone());
+    }
+    let keyring = &OsKeyring;
+    let stored = match keyring.get(service_name(), &account(DEVICE_ID_KEY)) {
+        Ok(id) => Some(id),
+        Err(keyring::Error::NoEntry) => secret_file::get(DEVICE_ID_KEY)?,
+        Err(e) if keyring_unavailable(&e) => {
+            secret_file::get(DEVICE_ID_KEY).map_err(ChikenError::KeyringUnavailable)?
+        }
+        Err(e) => return Err(keyring_error("Failed to read device ID", e)),
+    };
+    let id = match stored {
+        Some(id) => id,
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            match keyring.set(service_name(), &account(DEVICE_ID_KEY), &id) {
+                Ok(()) => {}
+                Err(e) if keyring_unavailable(&e) => {
+                    secret_file::set(DEVICE_ID_KEY, &id).map_err(ChikenError::KeyringUnavailable)?
+                }
+                Err(e) => return Err(keyring_error("Failed to store device ID", e)),
+            }
+            tracing::info!("Generated new device ID");
+            id
+        }
+    };
+    Ok(DEVICE_ID.get_or_init(|| id).clone())
+}
+
+// Environment variables handed to the sidecar at spawn, so the backend gets its
+// keys without them crossing the webview or the localhost API. The default secret
+// holds the backend's JSON map of env vars (the entry keychain_loader.py reads
+// and writes); it is injected as CHIKEN_SECRET if it isn't one. Other
+// secrets whose key is already an env var name (OPENAI_API_KEY) pass through
+// as-is. Values must never be logged.
+pub fn sidecar_env() -> Result<Vec<(String, String)>, ChikenError> {
+    let mut env = Vec::new();
+    for key in list_secret_keys()? {
+        let Some(value) = get_secret(&key)? else {
+            continue;
+        };
+        if key == DEFAULT_KEY {
+            match serde_json::from_str::<HashMap<String, String>>(&value) {
+                Ok(vars) => env.extend(vars.into_iter().filter(|(name, _)| is_env_name(name))),
+                Err(_) => env.push(("CHIKEN_SECRET".to_string(), value)),
+            }
+        } else if is_env_name(&key) && key == key.to_ascii_uppercase() {
+            env.push((key, value));
+        }
+    }
+    Ok(env)
+}
+
+// Every stored secret value, including the individual entries of the default
+// secret's env var map, for redacting them from sidecar output
+pub fn secret_values() -> Result<Vec<String>, ChikenError> {
+    let mut values = Vec::new();
+    for key in list_secret_keys()? {
+        let Some(value) = get_secret(&key)? else {
+            continue;
+        };
+        if let Ok(vars) = serde_json::from_str::<HashMap<String, String>>(&value) {
+            values.extend(vars.into_values());
+        }
+        values.push(value);
+    }
+    Ok(values)
+}
+
+pub fn is_env_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn backend_info() -> BackendInfo {
+    // Reading the index is the cheapest call that exercises the keyring
+    let keyring_available = match OsKeyring.get(service_name(), &account(INDEX_KEY)) {
+        Ok(_) => true,
+        Err(e) => !keyring_unavailable(&e),
+    };
+    BackendInfo {
+        backend: if keyring_available { "keyring" } else { "file" },
+        file_path: secret_file::path()
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    // Fails every call the way keyring does when there is no keyring to talk to
+    struct NoKeyring(fn() -> keyring::Error);
+
+    impl Keyring for NoKeyring {
+        fn get(&self, _service: &str, _account: &str) -> keyring::Result<String> {
+            Err((self.0)())
+        }
+
+        fn set(&self, _service: &str, _account: &str, _value: &str) -> keyring::Result<()> {
+            Err((self.0)())
+        }
+
+        fn delete(&self, _service: &str, _account: &str) -> keyring::Result<()> {
+            Err((self.0)())
+        }
+    }
+
+    fn no_secret_service() -> keyring::Error {
+        keyring::Error::PlatformFailure("org.freedesktop.secrets was not provided".into())
+    }
+
+    fn locked_store() -> keyring::Error {
+        keyring::Error::NoStorageAccess("keychain is locked".into())
+    }
+
+    fn too_long() -> keyring::Error {
+        keyring::Error::TooLong("password".to_string(), 1)
+    }
+
+    // A working keyring, held in memory
+    #[derive(Default)]
+    struct MemoryKeyring(RefCell<HashMap<(String, String), String>>);
+
+    impl Keyring for MemoryKeyring {
+        fn get(&self, service: &str, account: &str) -> keyring::Result<String> {
+            let entries = self.0.borrow();
+            let key = (service.to_string(), account.to_string());
+            entries.get(&key).cloned().ok_or(keyring::Error::NoEntry)
+        }
+
+        fn set(&self, service: &str, account: &str, value: &str) -> keyring::Result<()> {
+            let key = (service.to_string(), account.to_string());
+            self.0.borrow_mut().insert(key, value.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, service: &str, account: &str) -> keyring::Result<()> {
+            let key = (service.to_string(), account.to_string());
+            self.0
+                .borrow_mut()
+                .remove(&key)
+                .map(|_| ())
+                .ok_or(keyring::Error::NoEntry)
+        }
+    }
+
+    // Point secret_file at a temp dir once per test run. Tests share that one
+    // file, so each takes the lock and uses keys of its own.
+    fn secret_file_lock() -> MutexGuard<'static, ()> {
+        static INIT: Once = Once::new();
+        static LOCK: Mutex<()> = Mutex::new(());
+        INIT.call_once(|| {
+            let dir =
+                std::env::temp_dir().join(format!("chiken-secret-store-{}", std::process::id()));
+            std::fs::remove_dir_all(&dir).ok();
+            secret_file::init(&dir, None);
+        });
+        LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn set_get_delete_fall_back_to_the_file_without_a_keyring() {
+        let _lock = secret_file_lock();
+        for keyring in [NoKeyring(no_secret_service), NoKeyring(locked_store)] {
+            store_secret(&keyring, "OPENAI_API_KEY", "sk-test").unwrap();
+            assert_eq!(
+                secret_file::get("OPENAI_API_KEY").unwrap().as_deref(),
+                Some("sk-test")
+            );
+            assert_eq!(
+                read_secret(&keyring, "OPENAI_API_KEY").unwrap().as_deref(),
+                Some("sk-test")
+            );
+
+            remove_secret(&keyring, "OPENAI_API_KEY").unwrap();
+            assert_eq!(secret_file::get("OPENAI_API_KEY").unwrap(), None);
+            assert_eq!(read_secret(&keyring, "OPENAI_API_KEY").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn other_keyring_errors_do_not_fall_back() {
+        let _lock = secret_file_lock();
+        let keyring = NoKeyring(too_long);
+        assert!(matches!(
+            store_secret(&keyring, "ANTHROPIC_API_KEY", "sk-test"),
+            Err(ChikenError::Keyring(_))
+        ));
+        assert_eq!(secret_file::get("ANTHROPIC_API_KEY").unwrap(), None);
+        assert!(read_secret(&keyring, "ANTHROPIC_API_KEY").is_err());
+        assert!(remove_secret(&keyring, "ANTHROPIC_API_KEY").is_err());
+    }
+
+    #[test]
+    fn keyring_takes_over_from_the_file_once_available() {
+        let _lock = secret_file_lock();
+        store_secret(&NoKeyring(no_secret_service), "GROQ_API_KEY", "old").unwrap();
+
+        // Still readable from the file while the keyring has no entry for it
+        let keyring = MemoryKeyring::default();
+        assert_eq!(
+            read_secret(&keyring, "GROQ_API_KEY").unwrap().as_deref(),
+            Some("old")
+        );
+
+        store_secret(&keyring, "GROQ_API_KEY", "new").unwrap();
+        assert_eq!(secret_file::get("GROQ_API_KEY").unwrap(), None);
+        assert_eq!(
+            read_secret(&keyring, "GROQ_API_KEY").unwrap().as_deref(),
+            Some("new")
+        );
+        assert_eq!(read_index(&keyring).unwrap(), vec!["GROQ_API_KEY"]);
+
+        remove_secret(&keyring, "GROQ_API_KEY").unwrap();
+        assert_eq!(read_secret(&keyring, "GROQ_API_KEY").unwrap(), None);
+        assert!(read_index(&keyring).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reserved_keys_are_refused() {
+        let keyring = MemoryKeyring::default();
+        assert!(store_secret(&keyring, INDEX_KEY, "[]").is_err());
+        assert!(store_secret(&keyring, DEVICE_ID_KEY, "id").is_err());
     }
 }
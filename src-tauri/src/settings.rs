@@ -0,0 +1,39 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+// Same store file the frontend opens in app/lib/tauri-store.ts
+const SETTINGS_STORE: &str = "settings.json";
+
+pub fn get<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Option<T> {
+    let store = app.store(SETTINGS_STORE).ok()?;
+    serde_json::from_value(store.get(key)?).ok()
+}
+
+// Every key/value pair in the store, e.g. for the diagnostics bundle
+pub fn entries(app: &AppHandle) -> Vec<(String, Value)> {
+    app.store(SETTINGS_STORE)
+        .map(|store| store.entries())
+        .unwrap_or_default()
+}
+
+pub fn set(app: &AppHandle, key: &str, value: impl Into<Value>) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    store.set(key, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+pub fn delete(app: &AppHandle, key: &str) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    store.delete(key);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
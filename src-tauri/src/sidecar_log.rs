@@ -0,0 +1,467 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+// sidecar.log rotates once it reaches MAX_LOG_SIZE, keeping MAX_LOG_FILES files
+// in total: sidecar.log, sidecar.log.1 and sidecar.log.2
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+const MAX_LOG_FILES: usize = 3;
+
+pub const LOG_FILE_NAME: &str = "sidecar.log";
+// Recent lines kept in memory for get_sidecar_log_buffer
+const LOG_BUFFER_LINES: usize = 200;
+
+// Set once a log file failure has been reported, so sidecar restarts don't
+// repeat the warning
+static FILE_FAILURE_REPORTED: AtomicBool = AtomicBool::new(false);
+
+// One line of sidecar output queued for the writer thread
+#[derive(Clone, Serialize)]
+pub struct LogLine {
+    pub stream: &'static str,
+    pub line: String,
+}
+
+// Ring buffer of the latest sidecar output, so a frontend whose listeners were
+// registered after the window loaded can replay the startup lines it missed
+#[derive(Default)]
+pub struct LogBuffer(Mutex<VecDeque<LogLine>>);
+
+impl LogBuffer {
+    pub fn push(&self, line: LogLine) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == LOG_BUFFER_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn snapshot(&self) -> Vec<LogLine> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+// Stderr lines kept for get_last_sidecar_exit
+const STDERR_HISTORY_LINES: usize = 100;
+
+// The latest stderr lines of every sidecar in this app run. Unlike the monitor's
+// own tail it isn't reset on spawn, so the lines leading up to a crash are still
+// there after the automatic restart.
+#[derive(Default)]
+pub struct StderrHistory(Mutex<VecDeque<String>>);
+
+impl StderrHistory {
+    pub fn push(&self, line: &str) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == STDERR_HISTORY_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    pub fn snapshot(&self) -> Vec<String> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+// Stdout lines on their way to the frontend, sent as one sidecar-stdout-batch
+// event once `max_lines` have accumulated or `interval` after the first of them
+pub struct StdoutBatch {
+    lines: Vec<String>,
+    flush_at: Option<Instant>,
+    max_lines: usize,
+    interval: Duration,
+}
+
+impl StdoutBatch {
+    pub fn new(max_lines: usize, interval: Duration) -> Self {
+        StdoutBatch {
+            lines: Vec::new(),
+            flush_at: None,
+            max_lines,
+            interval,
+        }
+    }
+
+    // Queue a line received at `now`, returning the batch if it is now full
+    pub fn push(&mut self, line: String, now: Instant) -> Option<Vec<String>> {
+        self.lines.push(line);
+        if self.lines.len() >= self.max_lines {
+            return self.take();
+        }
+        self.flush_at.get_or_insert(now + self.interval);
+        None
+    }
+
+    // The lines before a protocol line, which take effect right away and so
+    // must not overtake the output printed ahead of them
+    pub fn before_line(&mut self, line: &str) -> Option<Vec<String>> {
+        if line.starts_with("@@") {
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    // When the pending lines are due; None while there are none
+    pub fn deadline(&self) -> Option<Instant> {
+        self.flush_at
+    }
+
+    pub fn take(&mut self) -> Option<Vec<String>> {
+        self.flush_at = None;
+        if self.lines.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.lines))
+        }
+    }
+}
+
+struct RotatingLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+impl RotatingLog {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write_line(&mut self, stream: &str, line: &str) -> io::Result<()> {
+        if self.size >= MAX_LOG_SIZE {
+            self.rotate()?;
+        }
+        let line = format!("[{}] {}\n", stream, line.trim_end());
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Shift sidecar.log.N to .N+1, dropping the oldest, and start a fresh sidecar.log
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..MAX_LOG_FILES).rev() {
+            let from = if index == 1 {
+                self.path.clone()
+            } else {
+                rotated_path(&self.path, index - 1)
+            };
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index))?;
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+// Open the log and hand writes off to a dedicated thread so file I/O never blocks
+// the sidecar monitor loop. The thread exits once every sender has been dropped.
+pub fn spawn_writer(path: PathBuf) -> io::Result<Sender<LogLine>> {
+    let mut log = RotatingLog::open(path)?;
+    let (tx, rx) = mpsc::channel::<LogLine>();
+    thread::Builder::new()
+        .name("sidecar-log".to_string())
+        .spawn(move || {
+            for entry in rx {
+                if let Err(e) = log.write_line(entry.stream, &entry.line) {
+                    tracing::warn!("Failed to write sidecar log: {}", e);
+                }
+            }
+        })?;
+    Ok(tx)
+}
+
+// Keep a line of output: in the log file when one could be opened, and in the
+// replay buffer either way
+pub fn record(log_file: Option<&Sender<LogLine>>, log_buffer: Option<&LogBuffer>, entry: LogLine) {
+    if let Some(log_file) = log_file {
+        log_file.send(entry.clone()).ok();
+    }
+    if let Some(log_buffer) = log_buffer {
+        log_buffer.push(entry);
+    }
+}
+
+// Tell the UI, once per run, that logs aren't reaching disk, e.g. because the log
+// directory can't be created on a locked-down system. Logging carries on to the
+// console and the in-memory buffer. The warning is buffered as well, since at
+// startup it is raised before the frontend listens.
+pub fn report_file_failure(app_handle: &tauri::AppHandle, error: &str) {
+    if FILE_FAILURE_REPORTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let message = format!("Logs are not being written to disk: {}", error);
+    if let Some(log_buffer) = app_handle.try_state::<LogBuffer>() {
+        log_buffer.push(LogLine {
+            stream: "tauri",
+            line: message.clone(),
+        });
+    }
+    app_handle
+        .emit(
+            "sidecar-log",
+            LevelledLine {
+                level: LogLevel::Warning,
+                message,
+            },
+        )
+        .ok();
+}
+
+// Ordered from least to most severe
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+// Payload of the sidecar-log event
+#[derive(Clone, Serialize)]
+pub struct LevelledLine {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+// Level names as Python's logging and uvicorn print them. Only the start of a
+// line is searched so a message that merely mentions "error" isn't promoted.
+const LEVEL_NAMES: [(&str, LogLevel); 7] = [
+    ("CRITICAL", LogLevel::Critical),
+    ("FATAL", LogLevel::Critical),
+    ("ERROR", LogLevel::Error),
+    ("WARNING", LogLevel::Warning),
+    ("WARN", LogLevel::Warning),
+    ("INFO", LogLevel::Info),
+    ("DEBUG", LogLevel::Debug),
+];
+const LEVEL_SEARCH_LEN: usize = 64;
+
+// Find a level name standing on its own near the start of the line, as in
+// "ERROR:    ...", "ERROR:root:...", "[WARNING] ..." or
+// "2024-05-01 12:00:00,123 - chiken - WARNING - ..."
+fn parse_level(line: &str) -> Option<LogLevel> {
+    let head = match line.char_indices().nth(LEVEL_SEARCH_LEN) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    };
+    head.split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(|word| {
+            LEVEL_NAMES
+                .iter()
+                .find(|(name, _)| *name == word)
+                .map(|(_, level)| *level)
+        })
+}
+
+// Assigns levels to stderr lines. Everything belonging to a multi-line record
+// (a traceback, or the indented continuation of a logged message) takes the
+// level of the line that opened it: a traceback printed by logger.exception()
+// is an ERROR throughout, and an uncaught one is treated as one too.
+#[derive(Default)]
+pub struct LevelParser {
+    // Level of the record the previous line belonged to
+    current: Option<LogLevel>,
+    // Inside a "Traceback (most recent call last):" block
+    in_traceback: bool,
+}
+
+impl LevelParser {
+    pub fn parse(&mut self, line: &str) -> LogLevel {
+        let trimmed = line.trim_end();
+        let continues = trimmed.is_empty() || trimmed.starts_with([' ', '\t']);
+        if trimmed.starts_with("Traceback (most recent call last)") {
+            // A traceback right after an INFO line wasn't logged on purpose
+            let level = self
+                .current
+                .filter(|level| *level >= LogLevel::Warning)
+                .unwrap_or(LogLevel::Error);
+            self.current = Some(level);
+            self.in_traceback = true;
+            return level;
+        }
+        // Chained exceptions restart the traceback under the same record
+        if trimmed.starts_with("During handling of the above exception")
+            || trimmed.starts_with("The above exception was the direct cause")
+        {
+            if let Some(level) = self.current {
+                self.in_traceback = true;
+                return level;
+            }
+        }
+        if let Some(level) = self.current.filter(|_| continues) {
+            return level;
+        }
+        if self.in_traceback {
+            // The "SomeError: message" line closes the traceback; a chained
+            // exception header may still follow it
+            self.in_traceback = false;
+            if let Some(level) = self.current {
+                return level;
+            }
+        }
+        let level = parse_level(trimmed);
+        self.current = level;
+        level.unwrap_or(LogLevel::Info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn burst_is_coalesced_into_full_batches() {
+        let mut batch = StdoutBatch::new(3, INTERVAL);
+        let now = Instant::now();
+        let mut emitted = Vec::new();
+        for i in 0..7 {
+            emitted.extend(batch.push(format!("line {}", i), now));
+        }
+        emitted.extend(batch.take());
+        assert_eq!(
+            emitted,
+            vec![
+                vec!["line 0", "line 1", "line 2"],
+                vec!["line 3", "line 4", "line 5"],
+                vec!["line 6"],
+            ]
+        );
+    }
+
+    #[test]
+    fn full_batch_clears_the_deadline() {
+        let mut batch = StdoutBatch::new(2, INTERVAL);
+        let now = Instant::now();
+        assert_eq!(batch.push("a".to_string(), now), None);
+        assert!(batch.deadline().is_some());
+        assert_eq!(
+            batch.push("b".to_string(), now),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(batch.deadline(), None);
+        assert_eq!(batch.take(), None);
+    }
+
+    #[test]
+    fn deadline_is_one_interval_after_the_first_line() {
+        let mut batch = StdoutBatch::new(200, INTERVAL);
+        assert_eq!(batch.deadline(), None);
+        let first = Instant::now();
+        batch.push("a".to_string(), first);
+        // Later lines ride along instead of pushing the flush back
+        batch.push("b".to_string(), first + Duration::from_millis(60));
+        batch.push("c".to_string(), first + Duration::from_millis(90));
+        assert_eq!(batch.deadline(), Some(first + INTERVAL));
+
+        assert_eq!(batch.take().map(|lines| lines.len()), Some(3));
+        assert_eq!(batch.deadline(), None);
+        let next = first + Duration::from_millis(150);
+        batch.push("d".to_string(), next);
+        assert_eq!(batch.deadline(), Some(next + INTERVAL));
+    }
+
+    #[test]
+    fn protocol_line_flushes_pending_lines_first() {
+        let mut batch = StdoutBatch::new(200, INTERVAL);
+        let now = Instant::now();
+        batch.push("loading".to_string(), now);
+        batch.push("still loading".to_string(), now);
+        assert_eq!(batch.before_line("plain output"), None);
+        assert_eq!(
+            batch.before_line("@@ready@@"),
+            Some(vec!["loading".to_string(), "still loading".to_string()])
+        );
+        assert_eq!(batch.deadline(), None);
+        // Nothing pending, nothing to send
+        assert_eq!(batch.before_line("@@badge@@1"), None);
+    }
+
+    #[test]
+    fn log_writer_reports_an_unusable_log_dir() {
+        let parent =
+            std::env::temp_dir().join(format!("chiken-sidecar-log-{}", std::process::id()));
+        fs::create_dir_all(&parent).unwrap();
+        // A file where the log directory should be can't be created over, even by root
+        let not_a_dir = parent.join("logs");
+        fs::write(&not_a_dir, "").unwrap();
+        assert!(spawn_writer(not_a_dir.join(LOG_FILE_NAME)).is_err());
+        fs::remove_dir_all(&parent).ok();
+    }
+
+    #[test]
+    fn stdout_without_a_log_file_still_reaches_buffer_and_frontend() {
+        let parent =
+            std::env::temp_dir().join(format!("chiken-sidecar-log-none-{}", std::process::id()));
+        fs::create_dir_all(&parent).unwrap();
+        let not_a_dir = parent.join("logs");
+        fs::write(&not_a_dir, "").unwrap();
+        // What spawn_and_monitor_sidecar ends up with when the writer can't start
+        let log_file = spawn_writer(not_a_dir.join(LOG_FILE_NAME)).ok();
+        assert!(log_file.is_none());
+
+        let log_buffer = LogBuffer::default();
+        let mut batch = StdoutBatch::new(2, INTERVAL);
+        let now = Instant::now();
+        let mut emitted = Vec::new();
+        // The same steps SidecarMonitor::on_stdout takes for ordinary lines
+        for line in ["starting", "loading model", "listening"] {
+            let entry = LogLine {
+                stream: "stdout",
+                line: line.to_string(),
+            };
+            record(log_file.as_ref(), Some(&log_buffer), entry);
+            emitted.extend(batch.push(line.to_string(), now));
+        }
+        emitted.extend(batch.take());
+
+        let buffered: Vec<_> = log_buffer
+            .snapshot()
+            .into_iter()
+            .map(|entry| (entry.stream, entry.line))
+            .collect();
+        assert_eq!(
+            buffered,
+            vec![
+                ("stdout", "starting".to_string()),
+                ("stdout", "loading model".to_string()),
+                ("stdout", "listening".to_string()),
+            ]
+        );
+        assert_eq!(
+            emitted,
+            vec![vec!["starting", "loading model"], vec!["listening"]]
+        );
+        fs::remove_dir_all(&parent).ok();
+    }
+}
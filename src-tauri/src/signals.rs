@@ -0,0 +1,30 @@
+// Exit on Ctrl-C or SIGTERM through app_handle.exit, so the sidecar is shut down
+// by the same ExitRequested path as quitting from the UI. Without this a Ctrl-C
+// in the terminal running the app kills it outright and the sidecar is left
+// behind. The graceful stop waits for the sidecar's Terminated event, which the
+// shell plugin only sends after it has waited on the child, so it is reaped
+// rather than left a zombie.
+pub async fn exit_on_signal(app_handle: tauri::AppHandle) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::warn!("Failed to listen for Ctrl-C: {}", e);
+        return;
+    }
+    tracing::info!("Received a shutdown signal, exiting.");
+    app_handle.exit(0);
+}
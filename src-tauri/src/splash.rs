@@ -0,0 +1,33 @@
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+
+// The main window starts hidden (visible: false in tauri.conf.json) because the
+// pyinstaller onefile sidecar takes several seconds to unpack. This small
+// frameless window shows startup progress until the backend is ready, or the
+// failure with a way to the logs if it never gets there.
+pub const LABEL: &str = "splash";
+
+pub fn create(app: &tauri::App) -> tauri::Result<()> {
+    WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("splash.html".into()))
+        .title("ChiKen")
+        .inner_size(420.0, 260.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .build()?;
+    Ok(())
+}
+
+// Close the splash (if still open) and reveal the main window in its place.
+// Only the first call after launch does anything, so later sidecar restarts
+// don't pop up a main window the user hid to the tray.
+pub fn finish(app_handle: &tauri::AppHandle) {
+    let Some(splash) = app_handle.get_webview_window(LABEL) else {
+        return;
+    };
+    crate::focus_main_window(app_handle);
+    if let Err(e) = splash.close() {
+        tracing::warn!("Failed to close splash window: {}", e);
+    }
+}
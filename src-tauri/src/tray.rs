@@ -0,0 +1,53 @@
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager};
+
+// Tray icon whose menu keeps the app (and the sidecar) reachable while the main
+// window is hidden, e.g. during long indexing jobs
+pub fn create_tray(app: &tauri::App) -> tauri::Result<()> {
+    let toggle = MenuItem::with_id(app, "toggle-window", "Show/Hide Window", true, None::<&str>)?;
+    let restart = MenuItem::with_id(
+        app,
+        "restart-backend",
+        "Restart Backend",
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&toggle, &restart, &quit])?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .tooltip("ChiKen")
+        .menu(&menu)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "toggle-window" => toggle_main_window(app),
+            "restart-backend" => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = crate::restart_sidecar(app).await {
+                        tracing::warn!("Failed to restart sidecar from tray: {}", e);
+                    }
+                });
+            }
+            // Goes through RunEvent::ExitRequested, which shuts the sidecar down gracefully
+            "quit" => app.exit(0),
+            _ => {}
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    Ok(())
+}
+
+// Hide the main window when it is showing, otherwise bring it back to the front
+fn toggle_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(false) {
+        window.hide().ok();
+    } else {
+        crate::focus_main_window(app);
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+// A crashed sidecar is caught by its Terminated event, a hung one (e.g. a
+// deadlocked backend) is not: the process stays alive but stops answering. This
+// task probes GET /health every `interval` and reports the sidecar as unhealthy
+// after `failure_threshold` misses in a row.
+#[derive(Clone, Copy)]
+struct WatchdogConfig {
+    interval: Duration,
+    failure_threshold: u32,
+    // Restart a hung sidecar right away instead of asking first
+    auto_restart: bool,
+}
+
+impl WatchdogConfig {
+    const DEFAULT: WatchdogConfig = WatchdogConfig {
+        interval: Duration::from_secs(15),
+        failure_threshold: 3,
+        auto_restart: false,
+    };
+
+    // The defaults overridden by backend.watchdog_interval_secs,
+    // backend.watchdog_failures and backend.watchdog_auto_restart, re-read on
+    // every round so changes apply without a restart
+    fn load(app_handle: &tauri::AppHandle) -> Self {
+        let get_u64 =
+            |key: &str| crate::settings::get::<u64>(app_handle, key).filter(|value| *value > 0);
+        WatchdogConfig {
+            interval: get_u64("backend.watchdog_interval_secs")
+                .map(Duration::from_secs)
+                .unwrap_or(Self::DEFAULT.interval),
+            failure_threshold: get_u64("backend.watchdog_failures")
+                .and_then(|failures| u32::try_from(failures).ok())
+                .unwrap_or(Self::DEFAULT.failure_threshold),
+            auto_restart: crate::settings::get::<bool>(app_handle, "backend.watchdog_auto_restart")
+                .unwrap_or(Self::DEFAULT.auto_restart),
+        }
+    }
+}
+
+// Payload of the sidecar-unhealthy event, also sent as sidecar-unresponsive for
+// listeners of the earlier name
+#[derive(Clone, Serialize)]
+struct SidecarUnhealthy {
+    pid: Option<u32>,
+    failures: u32,
+    last_error: String,
+    // Whether the watchdog is restarting the sidecar on its own
+    restarting: bool,
+}
+
+// Outcome of the most recent watchdog probe, reported by sidecar_status
+#[derive(Clone, Serialize)]
+pub struct HealthCheck {
+    // Unix seconds
+    pub at: u64,
+    pub healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn record(state: &crate::SidecarHandle, generation: u64, error: Option<&str>) {
+    let mut sidecar = state.lock().unwrap();
+    if sidecar.generation != generation {
+        return;
+    }
+    sidecar.last_health_check = Some(HealthCheck {
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0),
+        healthy: error.is_none(),
+        error: error.map(str::to_string),
+    });
+}
+
+// Runs for the lifetime of the app. Only a bundled sidecar that has been ready
+// once is probed; startup is covered by wait_for_sidecar_ready and an external
+// backend isn't ours to restart.
+pub async fn run(app_handle: tauri::AppHandle) {
+    let Some(state) = app_handle.try_state::<crate::SidecarHandle>() else {
+        return;
+    };
+    let state = state.inner().clone();
+    let mut watched_generation = 0;
+    let mut failures = 0;
+    // Set once reported, so one hang produces one event until the sidecar recovers
+    let mut reported = false;
+    loop {
+        let config = WatchdogConfig::load(&app_handle);
+        tokio::time::sleep(config.interval).await;
+        let (generation, port, pid) = {
+            let sidecar = state.lock().unwrap();
+            // Paused while the sidecar is being stopped or restarted on purpose
+            if sidecar.child.is_none()
+                || !sidecar.ready
+                || sidecar.stopping
+                || sidecar.restarting
+                || sidecar.external_url.is_some()
+            {
+                failures = 0;
+                reported = false;
+                continue;
+            }
+            (sidecar.generation, sidecar.port, sidecar.pid)
+        };
+        if generation != watched_generation {
+            watched_generation = generation;
+            failures = 0;
+            reported = false;
+        }
+        let Some(port) = port else {
+            continue;
+        };
+        let base_url = format!("http://localhost:{}", port);
+        let error = match crate::probe_backend_health(&base_url, Some(crate::backend_token())).await
+        {
+            crate::HealthProbe::Healthy => {
+                record(&state, generation, None);
+                failures = 0;
+                reported = false;
+                continue;
+            }
+            crate::HealthProbe::Unreachable(e) => e,
+            crate::HealthProbe::Unhealthy(status) => format!("HTTP {}", status),
+        };
+        // The sidecar may have been stopped or restarted while the probe ran
+        let current = {
+            let sidecar = state.lock().unwrap();
+            sidecar.child.is_some() && sidecar.generation == generation
+        };
+        if !current {
+            continue;
+        }
+        record(&state, generation, Some(&error));
+        failures += 1;
+        tracing::warn!(
+            "Sidecar health check failed ({} in a row): {}",
+            failures,
+            error
+        );
+        if failures >= config.failure_threshold && !reported {
+            reported = true;
+            // Nobody is there to answer the dialog in headless mode
+            let restarting = config.auto_restart || crate::headless::enabled();
+            let event = SidecarUnhealthy {
+                pid,
+                failures,
+                last_error: error,
+                restarting,
+            };
+            app_handle.emit("sidecar-unhealthy", event.clone()).ok();
+            app_handle.emit("sidecar-unresponsive", event).ok();
+            if restarting {
+                tracing::warn!("Restarting unresponsive sidecar");
+                restart(app_handle.clone());
+            } else {
+                offer_restart(&app_handle, failures);
+            }
+        }
+    }
+}
+
+fn offer_restart(app_handle: &tauri::AppHandle, failures: u32) {
+    let restart_handle = app_handle.clone();
+    app_handle
+        .dialog()
+        .message(format!(
+            "The backend has stopped responding ({} health checks failed in a row). \
+             Restart it now?",
+            failures
+        ))
+        .title("ChiKen backend not responding")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Restart".to_string(),
+            "Wait".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                restart(restart_handle);
+            }
+        });
+}
+
+fn restart(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::restart_sidecar(app_handle).await {
+            tracing::warn!("Failed to restart unresponsive sidecar: {}", e);
+        }
+    });
+}
@@ -0,0 +1,76 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Manager;
+use tauri_plugin_http::reqwest::{self, StatusCode};
+
+const ZOTERO_URL: &str = "http://127.0.0.1:23119";
+// The probes run in parallel and Zotero answers locally, so the whole check
+// stays well under a second even when nothing is listening
+const PROBE_TIMEOUT: Duration = Duration::from_millis(700);
+const DATABASE_FILE: &str = "zotero.sqlite";
+
+#[derive(Serialize)]
+pub struct ZoteroStatus {
+    // Folder holding zotero.sqlite, None if none of the candidates has one
+    pub data_dir: Option<String>,
+    // Zotero is running (the connector server answered /connector/ping)
+    pub running: bool,
+    // The local API the backend imports through is enabled (Settings → Advanced →
+    // "Allow other applications on this computer to communicate with Zotero").
+    // None when Zotero isn't running, since it can't be checked then.
+    pub local_api_enabled: Option<bool>,
+    // Better BibTeX answered its probe endpoint, or left its database in the
+    // data dir when Zotero isn't running
+    pub better_bibtex: bool,
+}
+
+// Zotero's default data dir is ~/Zotero on every OS; an override is tried first
+fn data_dir_candidates(
+    app_handle: &tauri::AppHandle,
+    override_path: Option<String>,
+) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = override_path.into_iter().map(PathBuf::from).collect();
+    if let Ok(home) = app_handle.path().home_dir() {
+        candidates.push(home.join("Zotero"));
+    }
+    candidates
+}
+
+async fn probe(client: &reqwest::Client, path: &str) -> Option<StatusCode> {
+    client
+        .get(format!("{}{}", ZOTERO_URL, path))
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .ok()
+        .map(|response| response.status())
+}
+
+pub async fn status(app_handle: &tauri::AppHandle, override_path: Option<String>) -> ZoteroStatus {
+    let data_dir = data_dir_candidates(app_handle, override_path)
+        .into_iter()
+        .find(|dir| dir.join(DATABASE_FILE).is_file());
+
+    let client = reqwest::Client::new();
+    let (ping, local_api, better_bibtex) = tokio::join!(
+        probe(&client, "/connector/ping"),
+        // 403 "Local API is not enabled" while the setting is off
+        probe(&client, "/api/users/0/items?limit=1"),
+        probe(&client, "/better-bibtex/cayw?probe=true"),
+    );
+    let running = ping.is_some_and(|status| status.is_success());
+    let local_api_enabled = running.then(|| local_api.is_some_and(|status| status.is_success()));
+    let better_bibtex = better_bibtex.is_some_and(|status| status.is_success())
+        || (!running
+            && data_dir
+                .as_ref()
+                .is_some_and(|dir| dir.join("better-bibtex.sqlite").is_file()));
+
+    ZoteroStatus {
+        data_dir: data_dir.map(|dir| dir.to_string_lossy().to_string()),
+        running,
+        local_api_enabled,
+        better_bibtex,
+    }
+}
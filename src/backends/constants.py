@@ -9,7 +9,10 @@ import sys
 
 def get_app_data_directory():
     """Get the application data directory, creating it if it doesn't exist."""
-    if getattr(sys, "frozen", False):
+    if os.getenv("CHIKEN_DATA_DIR"):
+        # Passed by the Tauri app, which owns the location
+        app_data_dir = os.environ["CHIKEN_DATA_DIR"]
+    elif getattr(sys, "frozen", False):
         # Running as a packaged app
         if sys.platform == "darwin":  # macOS
             app_data_dir = os.path.expanduser("~/Library/Application Support/ChiKen")
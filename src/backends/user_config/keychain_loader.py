@@ -3,15 +3,22 @@ Simple keychain loader for environment variables.
 Stores all env vars as a single JSON dict in keychain.
 """
 
-import getpass
 import json
 import os
 
 import keyring
 from loguru import logger
 
-SERVICE_NAME = "chiken"
-ENV_VARS_KEY = getpass.getuser()
+# Matches the Tauri app, which keeps each --profile's keys under its own service
+SERVICE_NAME = (
+    f"com.github.yuanjua.chiken.{os.environ['CHIKEN_PROFILE']}"
+    if os.getenv("CHIKEN_PROFILE")
+    else "com.github.yuanjua.chiken"
+)
+# The app's "default" secret (secret_store::DEFAULT_KEY). It used to be an
+# entry named after the OS user, which the app migrates to this one and removes
+# on startup, so reading that name again would find nothing.
+ENV_VARS_KEY = "chiken:default"
 
 
 def load_env_from_keychain(user_config=None) -> dict[str, str]:
@@ -1,14 +1,17 @@
 import argparse
 import asyncio
+import hmac
 import multiprocessing
 import os
 import sys
 import threading
+import time
 from contextlib import asynccontextmanager
 
 import uvicorn
-from fastapi import FastAPI
+from fastapi import FastAPI, Request
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 from loguru import logger
 
 from backends.api import router as api_router
@@ -33,11 +36,13 @@ load_dotenv(override=True)
 shutdown_event = asyncio.Event()
 main_loop = None
 
-# Set up loguru for console logging
+# Set up loguru for console logging, at the level picked in the desktop app
+LOG_LEVELS = {"error": "ERROR", "warn": "WARNING", "info": "INFO", "debug": "DEBUG"}
+LOG_LEVEL = LOG_LEVELS.get(os.getenv("CHIKEN_LOG_LEVEL", "info"), "INFO")
 logger.remove()  # Remove default handler
 logger.add(
     sys.stderr,
-    level="INFO",
+    level=LOG_LEVEL,
     format="{time:YYYY-MM-DD HH:mm:ss} {level} {name}: {message}",
     colorize=True,
 )
@@ -72,6 +77,15 @@ def stdin_monitor():
         os._exit(0)
 
 
+def announce_ready(server):
+    """Print the marker the Tauri app waits for once the server accepts connections."""
+    while not server.started:
+        if server.should_exit:
+            return
+        time.sleep(0.05)
+    print("@@ready@@", flush=True)
+
+
 @asynccontextmanager
 async def lifespan(app: FastAPI):
     """
@@ -161,6 +175,22 @@ logger.add(
     colorize=True,
 )
 
+# Set by the desktop app for every sidecar it spawns; requests must echo it back in
+# the X-Chiken-Token header. Unset when running the backend on its own.
+AUTH_TOKEN = os.getenv("CHIKEN_AUTH_TOKEN")
+
+
+# Registered before CORSMiddleware so that it runs inside it and rejections still
+# carry CORS headers the webview can read
+@app.middleware("http")
+async def require_auth_token(request: Request, call_next):
+    if AUTH_TOKEN and request.method != "OPTIONS":
+        token = request.headers.get("x-chiken-token", "")
+        if not hmac.compare_digest(token, AUTH_TOKEN):
+            return JSONResponse(status_code=401, content={"detail": "Invalid or missing auth token"})
+    return await call_next(request)
+
+
 app.add_middleware(
     CORSMiddleware,
     allow_origin_regex=r"^(https?://(localhost|127\.0\.0\.1|tauri\.localhost)(:\d+)?|tauri://localhost)$",
@@ -212,8 +242,6 @@ if __name__ == "__main__":
         )
 
     else:
-        uvicorn.run(
-            app,
-            host=args.host,
-            port=args.port,
-        )
+        server = uvicorn.Server(uvicorn.Config(app, host=args.host, port=args.port))
+        threading.Thread(target=announce_ready, args=(server,), daemon=True).start()
+        server.run()